@@ -10,6 +10,9 @@ MEMORY
   FLASH(rx) : ORIGIN = 0x08000000, LENGTH = 2048K
   RAM(rwx)  : ORIGIN = 0x20000000, LENGTH = 192K
 
+  /* IS42S16400J on FMC SDRAM bank 2, usable once sdram::init has run */
+  SDRAM(rwx) : ORIGIN = 0xD0000000, LENGTH = 8M
+
   /* More memory regions can declared: for example this is a second RAM region */
   /* CCRAM : ORIGIN = 0x10000000, LENGTH = 8K */
 }
@@ -21,3 +24,15 @@ MEMORY
 /* The location of the .text section can be overridden using the `_stext` symbol.
    By default it will place after .vector_table */
 /* _stext = ORIGIN(FLASH) + 0x40c; */
+
+/* Statics marked #[link_section = ".sdram"] are placed in external SDRAM. The section
+   is NOLOAD: the runtime neither copies nor zeroes it, so its contents are undefined
+   until written. */
+SECTIONS
+{
+  .sdram (NOLOAD) : ALIGN(4)
+  {
+    *(.sdram .sdram.*);
+    . = ALIGN(4);
+  } > SDRAM
+} INSERT AFTER .bss;
@@ -0,0 +1,414 @@
+//! Clock tree arithmetic for SYSCLK, the bus clocks and the LCD-TFT pixel clock
+//!
+//! Everything here is `const fn`, so clock settings can be derived and checked at
+//! compile time instead of being worked out by hand next to `init_system_clocks`. The
+//! results are plain numbers; the firmware turns them into the HAL's RCC configuration.
+
+use core::fmt;
+
+use crate::drivers::ili9341::PanelTiming;
+
+/// External crystal on the Discovery board
+pub const HSE_HZ: u32 = 8_000_000;
+
+/// HSE crystal range
+const HSE_MIN_HZ: u32 = 4_000_000;
+const HSE_MAX_HZ: u32 = 26_000_000;
+/// PLL input range after the shared PLLM divider
+const PLL_INPUT_MIN_HZ: u32 = 1_000_000;
+const PLL_INPUT_MAX_HZ: u32 = 2_000_000;
+/// PLLM range
+const PLLM_MIN: u32 = 2;
+const PLLM_MAX: u32 = 63;
+/// PLLN range, RM0090 6.3.2
+const PLLN_MIN: u32 = 50;
+const PLLN_MAX: u32 = 432;
+/// PLLQ range
+const PLLQ_MIN: u32 = 2;
+const PLLQ_MAX: u32 = 15;
+/// Bus clock limits with the regulator in scale 1, over-drive off. Over-drive would
+/// allow 180/45/90 MHz but has to be switched on through PWR first, which
+/// `init_system_clocks` does not do.
+const SYSCLK_MAX_HZ: u32 = 168_000_000;
+const APB1_MAX_HZ: u32 = 42_000_000;
+const APB2_MAX_HZ: u32 = 84_000_000;
+/// 48 MHz domain feeding USB OTG FS, SDIO and the RNG
+const PLL48_HZ: u32 = 48_000_000;
+/// HCLK per flash wait state at 2.7 to 3.6 V, RM0090 table 11
+const FLASH_HZ_PER_WS: u32 = 30_000_000;
+
+/// PLLSAIN range, RM0090 6.3.24
+const PLLSAIN_MIN: u32 = 50;
+const PLLSAIN_MAX: u32 = 432;
+/// PLLSAIR range
+const PLLSAIR_MIN: u32 = 2;
+const PLLSAIR_MAX: u32 = 7;
+/// VCO output range, the same for the main PLL and PLLSAI
+const VCO_MIN_HZ: u32 = 100_000_000;
+const VCO_MAX_HZ: u32 = 432_000_000;
+/// PLLSAIDIVR settings, RM0090 6.3.25
+const LCD_DIVS: [u32; 4] = [2, 4, 8, 16];
+
+/// Why [`ClockTreeBuilder::build`] could not produce a clock tree
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ClockError {
+    /// HSE outside the 4 to 26 MHz the oscillator accepts
+    Hse,
+    /// SYSCLK above 168 MHz, or no PLLM/PLLN/PLLP keeps the VCO in range and hits it
+    /// exactly
+    Sysclk,
+    /// A requested bus clock is above its limit
+    Apb1,
+    Apb2,
+    /// No PLLQ keeps the 48 MHz domain at or below 48 MHz, or exactly on it when USB
+    /// is required
+    Pll48,
+}
+
+/// Clock tree out of the HSE through the main PLL
+///
+/// Build it with [`ClockTreeBuilder`]; every frequency in here is what the hardware
+/// will actually run at, so it can be logged instead of documented by hand.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ClockTree {
+    pub hse_hz: u32,
+    pub pllm: u32,
+    pub plln: u32,
+    pub pllp: u32,
+    pub pllq: u32,
+    pub vco_hz: u32,
+    pub sysclk_hz: u32,
+    pub hclk_hz: u32,
+    pub apb1_div: u32,
+    pub apb1_hz: u32,
+    pub apb2_div: u32,
+    pub apb2_hz: u32,
+    pub pll48_hz: u32,
+    pub flash_wait_states: u32,
+}
+
+impl fmt::Display for ClockTree {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "HSE {} Hz, PLL_M {}, VCO {} Hz", self.hse_hz, self.pllm, self.vco_hz)?;
+        writeln!(f, "PLL_N {}, PLL_P {}, PLL_Q {}", self.plln, self.pllp, self.pllq)?;
+        writeln!(f, "SYSCLK {} Hz, HCLK {} Hz", self.sysclk_hz, self.hclk_hz)?;
+        writeln!(f, "APB1 /{} {} Hz", self.apb1_div, self.apb1_hz)?;
+        writeln!(f, "APB2 /{} {} Hz", self.apb2_div, self.apb2_hz)?;
+        write!(f, "PLL48CLK {} Hz, flash latency {} WS", self.pll48_hz, self.flash_wait_states)
+    }
+}
+
+/// Smallest APB prescaler bringing `hclk_hz` down to `max_hz` or below
+const fn apb_div(hclk_hz: u32, max_hz: u32) -> u32 {
+    let mut div = 1;
+    while div < 16 && hclk_hz / div > max_hz {
+        div *= 2;
+    }
+    div
+}
+
+/// Derives PLL settings, bus prescalers and flash wait states from target frequencies
+///
+/// ```ignore
+/// const CLOCKS: ClockTree = match ClockTreeBuilder::new(HSE_HZ).sysclk(72_000_000).build() {
+///     Ok(clocks) => clocks,
+///     Err(_) => panic!("unreachable clock tree"),
+/// };
+/// ```
+///
+/// Used in a `const` like above, an impossible request fails the build.
+#[derive(Clone, Copy, Debug)]
+pub struct ClockTreeBuilder {
+    hse_hz: u32,
+    sysclk_hz: u32,
+    apb1_hz: u32,
+    apb2_hz: u32,
+    usb: bool,
+}
+
+impl ClockTreeBuilder {
+    /// Start from the HSE crystal, with SYSCLK at 16 MHz and the bus clocks at their
+    /// limits
+    pub const fn new(hse_hz: u32) -> Self {
+        Self {
+            hse_hz,
+            sysclk_hz: 16_000_000,
+            apb1_hz: APB1_MAX_HZ,
+            apb2_hz: APB2_MAX_HZ,
+            usb: false,
+        }
+    }
+
+    /// SYSCLK, which HCLK follows undivided
+    pub const fn sysclk(self, hz: u32) -> Self {
+        Self { sysclk_hz: hz, ..self }
+    }
+
+    /// Upper bound for PCLK1, the smallest prescaler staying at or below it is chosen
+    pub const fn apb1(self, hz: u32) -> Self {
+        Self { apb1_hz: hz, ..self }
+    }
+
+    /// Upper bound for PCLK2, the smallest prescaler staying at or below it is chosen
+    pub const fn apb2(self, hz: u32) -> Self {
+        Self { apb2_hz: hz, ..self }
+    }
+
+    /// Require exactly 48 MHz on PLL48CLK for USB OTG FS
+    pub const fn usb(self) -> Self {
+        Self { usb: true, ..self }
+    }
+
+    pub const fn build(&self) -> Result<ClockTree, ClockError> {
+        if self.hse_hz < HSE_MIN_HZ || self.hse_hz > HSE_MAX_HZ {
+            return Err(ClockError::Hse);
+        }
+        if self.sysclk_hz > SYSCLK_MAX_HZ {
+            return Err(ClockError::Sysclk);
+        }
+        if self.apb1_hz > APB1_MAX_HZ {
+            return Err(ClockError::Apb1);
+        }
+        if self.apb2_hz > APB2_MAX_HZ {
+            return Err(ClockError::Apb2);
+        }
+
+        // Lowest PLLM first: a 2 MHz PLL input gives the least jitter
+        let mut found_sysclk = false;
+        let mut pllm = PLLM_MIN;
+        while pllm <= PLLM_MAX {
+            let input_hz = self.hse_hz / pllm;
+            if self.hse_hz.is_multiple_of(pllm) &&
+                input_hz >= PLL_INPUT_MIN_HZ &&
+                input_hz <= PLL_INPUT_MAX_HZ
+            {
+                let mut pllp = 2;
+                while pllp <= 8 {
+                    let vco_hz = self.sysclk_hz * pllp;
+                    let plln = vco_hz / input_hz;
+                    if vco_hz.is_multiple_of(input_hz) &&
+                        plln >= PLLN_MIN &&
+                        plln <= PLLN_MAX &&
+                        vco_hz >= VCO_MIN_HZ &&
+                        vco_hz <= VCO_MAX_HZ
+                    {
+                        found_sysclk = true;
+                        let pllq = vco_hz.div_ceil(PLL48_HZ);
+                        let pll48_ok = !self.usb || vco_hz.is_multiple_of(PLL48_HZ);
+                        if pllq >= PLLQ_MIN && pllq <= PLLQ_MAX && pll48_ok {
+                            return Ok(self.tree(pllm, plln, pllp, pllq));
+                        }
+                    }
+                    pllp += 2;
+                }
+            }
+            pllm += 1;
+        }
+
+        Err(if found_sysclk { ClockError::Pll48 } else { ClockError::Sysclk })
+    }
+
+    const fn tree(&self, pllm: u32, plln: u32, pllp: u32, pllq: u32) -> ClockTree {
+        let vco_hz = self.hse_hz / pllm * plln;
+        let hclk_hz = vco_hz / pllp;
+        let apb1_div = apb_div(hclk_hz, self.apb1_hz);
+        let apb2_div = apb_div(hclk_hz, self.apb2_hz);
+
+        ClockTree {
+            hse_hz: self.hse_hz,
+            pllm,
+            plln,
+            pllp,
+            pllq,
+            vco_hz,
+            sysclk_hz: hclk_hz,
+            hclk_hz,
+            apb1_div,
+            apb1_hz: hclk_hz / apb1_div,
+            apb2_div,
+            apb2_hz: hclk_hz / apb2_div,
+            pll48_hz: vco_hz / pllq,
+            flash_wait_states: (hclk_hz - 1) / FLASH_HZ_PER_WS,
+        }
+    }
+}
+
+/// PLLSAI settings for the LTDC pixel clock, the input being shared with the main PLL
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PllSaiConfig {
+    pub pllsain: u32,
+    /// SAI clock division, not involved in the pixel clock
+    pub pllsaiq: u32,
+    pub pllsair: u32,
+    /// Division applied to the R output before it reaches the LTDC, 2, 4, 8 or 16
+    pub lcd_div: u32,
+}
+
+/// LCD-TFT pixel clock out of `pllsai`, fed from the PLL input of `clocks`
+pub const fn pixel_clock_hz(clocks: &ClockTree, pllsai: &PllSaiConfig) -> u32 {
+    let vco_hz = (clocks.hse_hz / clocks.pllm) * pllsai.pllsain;
+    vco_hz / pllsai.pllsair / pllsai.lcd_div
+}
+
+/// Frames per second `timing` runs at with the pixel clock out of `pllsai`
+pub const fn refresh_rate(clocks: &ClockTree, pllsai: &PllSaiConfig, timing: &PanelTiming) -> u32 {
+    timing.refresh_rate(pixel_clock_hz(clocks, pllsai))
+}
+
+/// PLLSAI settings whose pixel clock brings `timing` closest to `refresh_hz`
+///
+/// The PLL input is shared with the main PLL, so only N, R and the LCD divider are
+/// searched; `pllsaiq` is passed through for the SAI clock. Returns `None` if the PLL
+/// input is outside the 1 to 2 MHz the VCO accepts.
+pub const fn solve_pllsai(
+    clocks: &ClockTree,
+    pllsaiq: u32,
+    timing: &PanelTiming,
+    refresh_hz: u32
+) -> Option<PllSaiConfig> {
+    let input_hz = clocks.hse_hz / clocks.pllm;
+    if input_hz < PLL_INPUT_MIN_HZ || input_hz > PLL_INPUT_MAX_HZ {
+        return None;
+    }
+
+    let target_hz = refresh_hz * timing.total_width() * timing.total_height();
+
+    // (pllsain, pllsair, lcd_div)
+    let mut best = (0, 0, 0);
+    let mut best_error = u32::MAX;
+    let mut d = 0;
+    while d < LCD_DIVS.len() {
+        let mut r = PLLSAIR_MIN;
+        while r <= PLLSAIR_MAX {
+            let mut n = PLLSAIN_MIN;
+            while n <= PLLSAIN_MAX {
+                let vco_hz = input_hz * n;
+                if vco_hz >= VCO_MIN_HZ && vco_hz <= VCO_MAX_HZ {
+                    let error = (vco_hz / r / LCD_DIVS[d]).abs_diff(target_hz);
+                    if error < best_error {
+                        best = (n, r, LCD_DIVS[d]);
+                        best_error = error;
+                    }
+                }
+                n += 1;
+            }
+            r += 1;
+        }
+        d += 1;
+    }
+
+    if best_error == u32::MAX {
+        return None;
+    }
+
+    let (pllsain, pllsair, lcd_div) = best;
+    Some(PllSaiConfig { pllsain, pllsaiq, pllsair, lcd_div })
+}
+
+// Checked at build time: the limits hold, and the solver lands on the requested rate
+// for the datasheet timing.
+const _: () = {
+    let too_fast = ClockTreeBuilder::new(HSE_HZ).sysclk(180_000_000);
+    assert!(matches!(too_fast.build(), Err(ClockError::Sysclk)));
+    let apb1_too_fast = ClockTreeBuilder::new(HSE_HZ).sysclk(72_000_000).apb1(45_000_000);
+    assert!(matches!(apb1_too_fast.build(), Err(ClockError::Apb1)));
+    let no_usb = ClockTreeBuilder::new(HSE_HZ).sysclk(100_000_000).usb();
+    assert!(matches!(no_usb.build(), Err(ClockError::Pll48)));
+
+    let Ok(clocks) = ClockTreeBuilder::new(HSE_HZ).sysclk(168_000_000).usb().build() else {
+        panic!("no clock tree for 168 MHz");
+    };
+    assert!(clocks.apb1_hz == 42_000_000 && clocks.apb2_hz == 84_000_000);
+    assert!(clocks.pll48_hz == 48_000_000 && clocks.flash_wait_states == 5);
+
+    let Some(pllsai) = solve_pllsai(&clocks, 4, &PanelTiming::DATASHEET, 60) else {
+        panic!("no PLLSAI settings for 60 Hz");
+    };
+    assert!(refresh_rate(&clocks, &pllsai, &PanelTiming::DATASHEET) == 60);
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TIMING: PanelTiming = PanelTiming::DATASHEET;
+
+    fn clocks_72mhz() -> ClockTree {
+        ClockTreeBuilder::new(HSE_HZ).sysclk(72_000_000).usb().build().unwrap()
+    }
+
+    #[test]
+    fn solve_exact() {
+        let clocks = clocks_72mhz();
+        let pllsai = solve_pllsai(&clocks, 4, &TIMING, 65).unwrap();
+        assert_eq!(pllsai.pllsaiq, 4);
+        assert_eq!(refresh_rate(&clocks, &pllsai, &TIMING), 65);
+    }
+
+    #[test]
+    fn solve_closest() {
+        let clocks = clocks_72mhz();
+        // 70 Hz needs 6_428_800 Hz, which no N/R/divider combination hits from 2 MHz
+        let pllsai = solve_pllsai(&clocks, 4, &TIMING, 70).unwrap();
+        assert_eq!(pllsai, PllSaiConfig { pllsain: 90, pllsaiq: 4, pllsair: 7, lcd_div: 4 });
+        assert_eq!(pixel_clock_hz(&clocks, &pllsai), 6_428_571);
+        assert_eq!(refresh_rate(&clocks, &pllsai, &TIMING), 70);
+
+        // Nothing closer anywhere in the search space
+        let target_hz = 70 * TIMING.total_width() * TIMING.total_height();
+        let error = pixel_clock_hz(&clocks, &pllsai).abs_diff(target_hz);
+        for pllsain in PLLSAIN_MIN..=PLLSAIN_MAX {
+            for pllsair in PLLSAIR_MIN..=PLLSAIR_MAX {
+                for lcd_div in LCD_DIVS {
+                    let candidate = PllSaiConfig { pllsain, pllsaiq: 4, pllsair, lcd_div };
+                    let vco_hz = clocks.hse_hz / clocks.pllm * pllsain;
+                    if (VCO_MIN_HZ..=VCO_MAX_HZ).contains(&vco_hz) {
+                        let candidate_error = pixel_clock_hz(&clocks, &candidate)
+                            .abs_diff(target_hz);
+                        assert!(candidate_error >= error);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn solve_pll_input_out_of_range() {
+        let clocks = clocks_72mhz();
+        // 8 MHz / 16 = 500 kHz and 8 MHz / 2 = 4 MHz
+        let slow = ClockTree { pllm: 16, ..clocks };
+        assert_eq!(solve_pllsai(&slow, 4, &TIMING, 60), None);
+        let fast = ClockTree { pllm: 2, ..clocks };
+        assert_eq!(solve_pllsai(&fast, 4, &TIMING, 60), None);
+        // The edges are accepted
+        let edge = ClockTree { hse_hz: 16_000_000, pllm: 16, ..clocks };
+        assert!(solve_pllsai(&edge, 4, &TIMING, 60).is_some());
+        let edge = ClockTree { pllm: 4, ..clocks };
+        assert!(solve_pllsai(&edge, 4, &TIMING, 60).is_some());
+    }
+
+    #[test]
+    fn bus_clocks() {
+        let builder = ClockTreeBuilder::new(HSE_HZ).sysclk(168_000_000);
+        let clocks = builder.apb1(21_000_000).apb2(42_000_000).build().unwrap();
+        assert_eq!((clocks.apb1_div, clocks.apb1_hz), (8, 21_000_000));
+        assert_eq!((clocks.apb2_div, clocks.apb2_hz), (4, 42_000_000));
+
+        // The smallest prescaler staying at or below the bound
+        let clocks = builder.apb1(30_000_000).apb2(84_000_000).build().unwrap();
+        assert_eq!((clocks.apb1_div, clocks.apb1_hz), (8, 21_000_000));
+        assert_eq!((clocks.apb2_div, clocks.apb2_hz), (2, 84_000_000));
+
+        assert_eq!(builder.apb1(43_000_000).build(), Err(ClockError::Apb1));
+        assert_eq!(builder.apb2(85_000_000).build(), Err(ClockError::Apb2));
+    }
+
+    #[test]
+    fn build_errors() {
+        assert_eq!(ClockTreeBuilder::new(3_000_000).build(), Err(ClockError::Hse));
+        assert_eq!(ClockTreeBuilder::new(27_000_000).build(), Err(ClockError::Hse));
+        // A 1 or 2 MHz PLL input times N over P never gives 100.3 MHz
+        let inexact = ClockTreeBuilder::new(HSE_HZ).sysclk(100_300_000);
+        assert_eq!(inexact.build(), Err(ClockError::Sysclk));
+    }
+}
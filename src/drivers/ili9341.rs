@@ -1,20 +1,303 @@
 #![allow(unused)]
 
+use core::fmt::Debug;
+
+use embedded_hal::{ delay::DelayNs, digital::{ self, Error as _, InputPin } };
 use log::info;
 
-use crate::LCD;
+use super::ili9341_regs::{
+    BacklightPins,
+    BacklightPwm,
+    BlankingPorchControl,
+    CabcMode,
+    ColumnAddress,
+    ControlDisplay,
+    DigitalGamma1,
+    DigitalGamma2,
+    DisplayFunctionControl,
+    DisplayId,
+    DisplayStatus,
+    DriverTimingA,
+    DriverTimingB,
+    Enable3Gamma,
+    FrameRateIdle,
+    FrameRateNormal,
+    FrameRatePartial,
+    GammaSet,
+    Id4,
+    InterfaceControl,
+    MemoryAccessControl,
+    NegativeGamma,
+    PageAddress,
+    PartialArea,
+    PixelFormatSet,
+    PositiveGamma,
+    PowerControl1,
+    PowerControl2,
+    PowerControlA,
+    PowerControlB,
+    PowerMode,
+    PowerOnSequence,
+    PumpRatioControl,
+    RangeError,
+    Register,
+    RgbInterfaceControl,
+    TearScanline,
+    TearingEffectOn,
+    VcomControl,
+    VcomOffset,
+    VerticalScrollDefinition,
+    VerticalScrollStart,
+};
 
-pub struct ILI9341 {
-    lcd: LCD,
-}
+/// Byte-level transport to the ILI9341 controller
+///
+/// Implemented by [`LCD`](super::lcd::LCD) for the 4-wire SPI interface; any other
+/// transport (a different board, a recording mock on the host) only has to provide
+/// these primitives to drive [`ILI9341`].
+pub trait Interface {
+    type Error: Debug;
 
-impl ILI9341 {
-    pub const ILI9341_LCD_PIXEL_WIDTH: usize = 240;
-    pub const ILI9341_LCD_PIXEL_HEIGHT: usize = 320;
+    /// Send a command byte (D/CX low)
+    fn write_reg(&mut self, reg: u8) -> Result<(), Self::Error>;
+    /// Send a parameter byte (D/CX high)
+    fn write_data(&mut self, data: u8) -> Result<(), Self::Error>;
+    /// Send a read command and clock `buf.len()` bytes back within the same chip select
+    ///
+    /// The bytes are returned exactly as they came off the wire, dummy cycles included.
+    fn read_data(&mut self, reg: u8, buf: &mut [u8]) -> Result<(), Self::Error>;
 
+    /// Send a run of parameter bytes (D/CX high)
     ///
-    /// @brief  ILI9341 Timing
-    /// Timing configuration  (Typical configuration from ILI9341 datasheet)
+    /// Defaults to one `write_data` per byte; transports that can keep the chip selected
+    /// for the whole run should override it.
+    fn write_data_slice(&mut self, data: &[u8]) -> Result<(), Self::Error> {
+        for &byte in data {
+            self.write_data(byte)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// ILI9341 driver error, generic over the transport error
+///
+/// For [`LCD`](super::lcd::LCD) the transport error is an
+/// [`LcdError`](super::lcd::LcdError) telling SPI bus failures from control pin failures.
+#[derive(Debug)]
+pub enum Ili9341Error<E> {
+    /// The transport failed
+    Interface(E),
+    /// ID4 read back as all zeros or all ones, nothing is answering on the bus
+    NotConnected,
+    /// Something answered, but it is not an ILI9341
+    UnexpectedId(Id4),
+    /// A register read back after init differs from what was written
+    Readback {
+        reg: u8,
+        expected: u8,
+        actual: u8,
+    },
+    /// A typed register parameter is out of range
+    Range(RangeError),
+    /// The TE input failed
+    TearSignal(digital::ErrorKind),
+    /// Waiting for TE while the tearing effect output is switched off
+    TearingEffectOff,
+    /// The requested power state cannot be reached from the current one
+    InvalidTransition {
+        from: PowerState,
+        to: PowerState,
+    },
+    /// GRAM is fed by the RGB interface, select [`InterfaceMode::Mcu`] to write it over SPI
+    RgbInterface,
+}
+
+impl<E> From<RangeError> for Ili9341Error<E> {
+    fn from(err: RangeError) -> Self {
+        Self::Range(err)
+    }
+}
+
+/// Source of the panel's tearing effect (TE) output
+///
+/// [`PolledTearSignal`] busy-polls a GPIO input; an EXTI driven implementation can
+/// sleep until the edge interrupt instead.
+pub trait TearSignal {
+    type Error: digital::Error;
+
+    /// Block until the rising edge of the next TE pulse
+    fn wait_for_tear(&mut self) -> Result<(), Self::Error>;
+}
+
+/// [`TearSignal`] polling a GPIO input for the rising edge
+pub struct PolledTearSignal<P>(pub P);
+
+impl<P: InputPin> TearSignal for PolledTearSignal<P> {
+    type Error = P::Error;
+
+    fn wait_for_tear(&mut self) -> Result<(), P::Error> {
+        while self.0.is_high()? {}
+        while self.0.is_low()? {}
+
+        Ok(())
+    }
+}
+
+/// When the TE output pulses
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TearingMode {
+    /// Once per frame, during V-blanking
+    VBlank,
+    /// During V-blanking and every H-blanking
+    VHBlank,
+}
+
+/// Dummy cycles the serial interface inserts between a read command and its data
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Dummy {
+    None,
+    /// One clock, used by the 24 and 32 bit reads (04h, 09h)
+    Bit,
+    /// One full parameter, used by the ID4 read (D3h)
+    Byte,
+}
+
+/// One step of an initialisation sequence: a command, its parameters and the time to
+/// wait before the next command is sent
+#[derive(Clone, Copy, Debug)]
+pub struct InitCommand {
+    pub cmd: u8,
+    pub params: &'static [u8],
+    pub delay_ms: u32,
+}
+
+impl InitCommand {
+    pub const fn new(cmd: u8, params: &'static [u8]) -> Self {
+        Self { cmd, params, delay_ms: 0 }
+    }
+
+    pub const fn delay(self, delay_ms: u32) -> Self {
+        Self { delay_ms, ..self }
+    }
+}
+
+/// What [`ILI9341::init`] reads back from the panel
+///
+/// Reading needs the controller's serial output. The STM32F429I-DISCO does not wire it
+/// to SPI5 MISO, so reads there return whatever the idle line holds and only `None`
+/// works on that board.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Verify {
+    /// Write blind, the power state is taken from the sequence
+    None,
+    /// Identify the panel through ID4 before writing anything
+    Id,
+    /// Identify the panel, then read MADCTL, COLMOD and the power mode back
+    Readback,
+}
+
+/// [`InitCommand`] writing a typed register, validated and encoded at compile time
+macro_rules! init_register {
+    ($reg:ident { $($fields:tt)* }) => {
+        init_register!($reg, $reg { $($fields)* })
+    };
+    ($reg:ty, $value:expr) => {
+        InitCommand::new(
+            <$reg as Register>::CMD,
+            &const {
+                let reg: $reg = $value;
+                assert!(reg.validate().is_ok(), "Invalid register in an init sequence!");
+                reg.encode()
+            }
+        )
+    };
+}
+
+/// Screen rotation, relative to the Discovery board held with the USB connector at the bottom
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Orientation {
+    Portrait,
+    Landscape,
+    PortraitFlipped,
+    LandscapeFlipped,
+}
+
+impl Orientation {
+    /// MV, MX and MY bits of MADCTL for this orientation, before mirroring
+    const fn madctl(self) -> (bool, bool, bool) {
+        match self {
+            Orientation::Portrait => (false, true, true),
+            Orientation::Landscape => (true, false, true),
+            Orientation::PortraitFlipped => (false, false, false),
+            Orientation::LandscapeFlipped => (true, true, false),
+        }
+    }
+
+    /// Split MADCTL into an orientation plus the mirroring on top of it
+    const fn from_madctl(madctl: MemoryAccessControl) -> (Self, bool, bool) {
+        let orientation = match (madctl.mv, madctl.my) {
+            (false, true) => Orientation::Portrait,
+            (true, true) => Orientation::Landscape,
+            (false, false) => Orientation::PortraitFlipped,
+            (true, false) => Orientation::LandscapeFlipped,
+        };
+        let (_, mx, _) = orientation.madctl();
+
+        (orientation, madctl.mx != mx, false)
+    }
+}
+
+/// Order of the colour components on the panel's colour filter
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColorOrder {
+    Rgb,
+    Bgr,
+}
+
+/// 16-bit colour, 5 bits red, 6 bits green and 5 bits blue from MSB to LSB
+///
+/// Red and blue land on the panel as selected with [`ILI9341::set_color_order`]. Laid out
+/// as a bare `u16`, so a slice of it is also a valid LTDC RGB565 framebuffer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(transparent)]
+pub struct Rgb565(pub u16);
+
+impl Rgb565 {
+    pub const BLACK: Self = Self(0x0000);
+    pub const WHITE: Self = Self(0xffff);
+    pub const RED: Self = Self(0xf800);
+    pub const GREEN: Self = Self(0x07e0);
+    pub const BLUE: Self = Self(0x001f);
+
+    /// From 8-bit components, dropping their low bits
+    pub const fn new(r: u8, g: u8, b: u8) -> Self {
+        Self((((r >> 3) as u16) << 11) | (((g >> 2) as u16) << 5) | ((b >> 3) as u16))
+    }
+
+    /// Components MSB-aligned in a byte each, as sent over the 18 bits/pixel interface
+    pub const fn to_rgb666(self) -> [u8; 3] {
+        [((self.0 >> 8) as u8) & 0xf8, ((self.0 >> 3) as u8) & 0xfc, (self.0 << 3) as u8]
+    }
+}
+
+/// Sync pulse widths and porches of a frame, in pixel clocks horizontally and in lines
+/// vertically
+///
+/// The same values drive the LTDC and, in RGB-SYNC mode, the controller's own porch
+/// counters, so keeping them here keeps the two ends of the RGB bus in step.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PanelTiming {
+    pub hsync: u16,
+    pub hbp: u16,
+    pub hfp: u16,
+    pub vsync: u16,
+    pub vbp: u16,
+    pub vfp: u16,
+}
+
+impl PanelTiming {
+    /// Typical configuration from the ILI9341 datasheet
     ///   HSYNC=10 (9+1)
     ///   HBP=20 (29-10+1)
     ///   ActiveW=240 (269-20-10+1)
@@ -24,23 +307,273 @@ impl ILI9341 {
     ///   VBP=2 (3-2+1)
     ///   ActiveH=320 (323-2-2+1)
     ///   VFP=4 (327-320-2-2+1)
+    pub const DATASHEET: Self = Self { hsync: 10, hbp: 20, hfp: 10, vsync: 2, vbp: 2, vfp: 4 };
+
+    /// Internal oscillator frequency, clocking the panel when it is not fed by DOTCLK
+    pub const FOSC_HZ: u32 = 615_000;
+
+    /// Active pixels per line on the RGB interface
+    ///
+    /// The panel always scans in its native portrait order, MADCTL only remaps GRAM
+    /// access over the MCU interface, so this holds in every [`Orientation`].
+    pub const ACTIVE_WIDTH: u16 = ILI9341::<(), ()>::ILI9341_LCD_PIXEL_WIDTH as u16;
+    /// Active lines per frame on the RGB interface, see [`ACTIVE_WIDTH`](Self::ACTIVE_WIDTH)
+    pub const ACTIVE_HEIGHT: u16 = ILI9341::<(), ()>::ILI9341_LCD_PIXEL_HEIGHT as u16;
+
+    /// Pixel clocks per line, blanking included
+    pub const fn total_width(&self) -> u32 {
+        (self.hsync + self.hbp + self.hfp + Self::ACTIVE_WIDTH) as u32
+    }
+
+    /// Lines per frame, blanking included
+    pub const fn total_height(&self) -> u32 {
+        (self.vsync + self.vbp + self.vfp + Self::ACTIVE_HEIGHT) as u32
+    }
+
+    /// Frames per second over the RGB interface with the LTDC pixel clock at
+    /// `pixel_clock_hz`, rounded to the nearest whole frame
+    pub const fn refresh_rate(&self, pixel_clock_hz: u32) -> u32 {
+        let pixels = self.total_width() * self.total_height();
+        (pixel_clock_hz + pixels / 2) / pixels
+    }
+
+    /// Blanking Porch Control values for RGB-SYNC mode, or the first porch the register
+    /// cannot hold
+    pub const fn porch(&self) -> Result<BlankingPorchControl, RangeError> {
+        let cmd = ILI9341::<(), ()>::LCD_BPC;
+        // Check the full width before narrowing, 0x104 would otherwise pass as 0x04
+        if self.vfp > 0x7f {
+            return Err(RangeError { cmd, field: "vfp" });
+        }
+        if self.vbp > 0x7f {
+            return Err(RangeError { cmd, field: "vbp" });
+        }
+        if self.hfp > 0x1f {
+            return Err(RangeError { cmd, field: "hfp" });
+        }
+        if self.hbp > 0x1f {
+            return Err(RangeError { cmd, field: "hbp" });
+        }
+
+        let porch = BlankingPorchControl {
+            vfp: self.vfp as u8,
+            vbp: self.vbp as u8,
+            hfp: self.hfp as u8,
+            hbp: self.hbp as u8,
+        };
+        match porch.validate() {
+            Ok(()) => Ok(porch),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Lines the internal clock scans per frame: the panel plus the porches, which the
+    /// controller uses in place of the sync pulses
+    const fn internal_lines(&self) -> u32 {
+        (self.vbp + self.vfp) as u32 + ILI9341::<(), ()>::ILI9341_LCD_PIXEL_HEIGHT as u32
+    }
+
+    /// Frames per second off the internal oscillator, as used in MCU mode, rounded to the
+    /// nearest whole frame
+    pub const fn internal_refresh_rate(&self, frame_rate: &FrameRateNormal) -> u32 {
+        let clocks = ((frame_rate.rtn as u32) << frame_rate.div) * self.internal_lines();
+        (Self::FOSC_HZ + clocks / 2) / clocks
+    }
+
+    /// Frame Rate Control values bringing the internal refresh rate closest to
+    /// `refresh_hz`
+    pub const fn frame_rate(&self, refresh_hz: u32) -> FrameRateNormal {
+        let mut best = FrameRateNormal { div: 0, rtn: 0x1f };
+        let mut best_error = u32::MAX;
+        let mut div = 0;
+        while div <= 0b11 {
+            let mut rtn = 0x10;
+            while rtn <= 0x1f {
+                let candidate = FrameRateNormal { div, rtn };
+                let error = self.internal_refresh_rate(&candidate).abs_diff(refresh_hz);
+                if error < best_error {
+                    best = candidate;
+                    best_error = error;
+                }
+                rtn += 1;
+            }
+            div += 1;
+        }
+        best
+    }
+}
+
+// `init` runs the panel with the datasheet porches
+const _: () = assert!(PanelTiming::DATASHEET.porch().is_ok());
+
+/// Analog gamma setup: one of the factory curves, fine-tuned by the positive and
+/// negative correction tables
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GammaCurve {
+    pub gamma_set: GammaSet,
+    pub positive: PositiveGamma,
+    pub negative: NegativeGamma,
+}
+
+impl GammaCurve {
+    /// ST's reference values for the Discovery board panel, applied by `init`
+    pub const ST_REFERENCE: Self = Self {
+        gamma_set: GammaSet { curve: 0x01 },
+        positive: PositiveGamma {
+            params: [
+                0x0f, 0x29, 0x24, 0x0c, 0x0e, 0x09, 0x4e, 0x78, 0x3c, 0x09, 0x13, 0x05, 0x17, 0x11,
+                0x00,
+            ],
+        },
+        negative: NegativeGamma {
+            params: [
+                0x00, 0x16, 0x1b, 0x04, 0x11, 0x07, 0x31, 0x33, 0x42, 0x05, 0x0c, 0x0a, 0x28, 0x2f,
+                0x0f,
+            ],
+        },
+    };
+
+    /// Values used by Adafruit's ILI9341 breakout, with more contrast in the mid-tones
+    pub const ADAFRUIT: Self = Self {
+        gamma_set: GammaSet { curve: 0x01 },
+        positive: PositiveGamma {
+            params: [
+                0x0f, 0x31, 0x2b, 0x0c, 0x0e, 0x08, 0x4e, 0xf1, 0x37, 0x07, 0x10, 0x03, 0x0e, 0x09,
+                0x00,
+            ],
+        },
+        negative: NegativeGamma {
+            params: [
+                0x00, 0x0e, 0x14, 0x03, 0x11, 0x07, 0x31, 0xc1, 0x48, 0x08, 0x0f, 0x0c, 0x31, 0x36,
+                0x0f,
+            ],
+        },
+    };
+}
+
+/// Rectangle of GRAM, corners inclusive, in the coordinates of the current orientation
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Window {
+    pub x0: u16,
+    pub y0: u16,
+    pub x1: u16,
+    pub y1: u16,
+}
+
+/// Path the pixel data takes into the panel
+///
+/// The RGB modes take pixels from the LTDC; `Mcu` takes them over SPI with
+/// [`ILI9341::write_pixels`] and runs the panel from its internal oscillator.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InterfaceMode {
+    /// RGB interface qualified by the DE signal, porches taken from the LTDC timing
+    RgbDe,
+    /// RGB interface framed by HSYNC/VSYNC alone, with the porches the panel expects
+    RgbSync(BlankingPorchControl),
+    /// MCU interface writing GRAM over SPI
+    Mcu,
+}
+
+impl InterfaceMode {
+    /// RGB Interface Signal Control, Interface Control and Display Function Control
+    /// for this mode
+    const fn registers(&self) -> (RgbInterfaceControl, InterfaceControl, DisplayFunctionControl) {
+        match self {
+            InterfaceMode::RgbDe => (
+                RgbInterfaceControl::decode([0xc2]),
+                InterfaceControl::decode([0x01, 0x00, 0x06]),
+                DisplayFunctionControl::decode([0x0a, 0xa7, 0x27, 0x04]),
+            ),
+            InterfaceMode::RgbSync(_) => (
+                RgbInterfaceControl::decode([0xe2]),
+                InterfaceControl::decode([0x01, 0x00, 0x06]),
+                DisplayFunctionControl::decode([0x0a, 0xa7, 0x27, 0x04]),
+            ),
+            InterfaceMode::Mcu => (
+                RgbInterfaceControl::decode([0x40]),
+                InterfaceControl::decode([0x01, 0x00, 0x00]),
+                DisplayFunctionControl::decode([0x0a, 0xa7, 0x27, 0x00]),
+            ),
+        }
+    }
+}
+
+/// Registers [`ILI9341::INIT_SEQUENCE`] sets the RGB interface up with, the same as
+/// [`ILI9341::set_interface_mode`] writes for [`InterfaceMode::RgbDe`]
+const RGB_DE: (RgbInterfaceControl, InterfaceControl, DisplayFunctionControl) =
+    InterfaceMode::RgbDe.registers();
+
+/// Power state of the panel
+///
+/// `On` and `Off` refer to the display output with the panel awake. Partial mode and
+/// idle mode are independent of each other, `PartialIdle` has both in effect; each is
+/// entered and left on its own. Any awake state can go to `Sleeping` and waking up
+/// returns to the state the panel was put to sleep from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PowerState {
+    Off,
+    Sleeping,
+    On,
+    Partial,
+    Idle,
+    PartialIdle,
+}
+
+impl PowerState {
+    const fn awake(display_on: bool, partial: bool, idle: bool) -> Self {
+        match (display_on, partial, idle) {
+            (false, _, _) => Self::Off,
+            (true, false, false) => Self::On,
+            (true, true, false) => Self::Partial,
+            (true, false, true) => Self::Idle,
+            (true, true, true) => Self::PartialIdle,
+        }
+    }
+}
+
+pub struct ILI9341<I, D> {
+    lcd: I,
+    delay: D,
+    sleeping: bool,
+    display_on: bool,
+    /// Partial mode (12h) rather than normal display mode (13h)
+    partial: bool,
+    idle: bool,
+    madctl: MemoryAccessControl,
+    orientation: Orientation,
+    mirror_x: bool,
+    mirror_y: bool,
+    scroll_area: VerticalScrollDefinition,
+    tearing: Option<TearingMode>,
+    pixel_format: PixelFormatSet,
+    interface_mode: InterfaceMode,
+}
+
+impl<I, D> ILI9341<I, D> {
+    pub const ILI9341_LCD_PIXEL_WIDTH: usize = 240;
+    pub const ILI9341_LCD_PIXEL_HEIGHT: usize = 320;
+
+    //
+    // @brief  ILI9341 Timing
+    // Timing configuration  (Typical configuration from ILI9341 datasheet)
+    //   see `PanelTiming::DATASHEET`
 
     /// Horizontal synchronization
-    pub const ILI9341_HSYNC: u16 = 10;
+    pub const ILI9341_HSYNC: u16 = PanelTiming::DATASHEET.hsync;
     /// Horizontal back porch
-    pub const ILI9341_HBP: u16 = 20;
+    pub const ILI9341_HBP: u16 = PanelTiming::DATASHEET.hbp;
     /// Horizontal front porch
-    pub const ILI9341_HFP: u16 = 10;
+    pub const ILI9341_HFP: u16 = PanelTiming::DATASHEET.hfp;
     /// Vertical synchronization
-    pub const ILI9341_VSYNC: u16 = 2;
+    pub const ILI9341_VSYNC: u16 = PanelTiming::DATASHEET.vsync;
     /// Vertical back porch
-    pub const ILI9341_VBP: u16 = 2;
+    pub const ILI9341_VBP: u16 = PanelTiming::DATASHEET.vbp;
     /// Vertical front porch
-    pub const ILI9341_VFP: u16 = 4;
+    pub const ILI9341_VFP: u16 = PanelTiming::DATASHEET.vfp;
 
-    ///
-    ///  @brief  ILI9341 Registers
-    ///
+    //
+    //  @brief  ILI9341 Registers
+    //
 
     /* Level 1 Commands */
 
@@ -130,140 +663,1050 @@ impl ILI9341 {
     pub const LCD_POWER_SEQ: u8 = 0xed; /* Power on sequence register */
     pub const LCD_3GAMMA_EN: u8 = 0xf2; /* 3 Gamma enable register */
     pub const LCD_PRC: u8 = 0xf7; /* Pump ratio control register */
+}
 
-    pub fn init(mut lcd: LCD) -> Self {
-        info!("Init ili9341 panel");
+impl<I: Interface, D: DelayNs> ILI9341<I, D> {
+    /// States with the display output on, partial and idle mode are changed from these
+    const DISPLAYING: [PowerState; 4] = [
+        PowerState::On,
+        PowerState::Partial,
+        PowerState::Idle,
+        PowerState::PartialIdle,
+    ];
+    const AWAKE: [PowerState; 5] = [
+        PowerState::Off,
+        PowerState::On,
+        PowerState::Partial,
+        PowerState::Idle,
+        PowerState::PartialIdle,
+    ];
 
-        lcd.write_reg(0xca);
-        lcd.write_data(0xc3);
-        lcd.write_data(0x08);
-        lcd.write_data(0x50);
-        lcd.write_reg(Self::LCD_POWERB);
-        lcd.write_data(0x00);
-        lcd.write_data(0xc1);
-        lcd.write_data(0x30);
-        lcd.write_reg(Self::LCD_POWER_SEQ);
-        lcd.write_data(0x64);
-        lcd.write_data(0x03);
-        lcd.write_data(0x12);
-        lcd.write_data(0x81);
-        lcd.write_reg(Self::LCD_DTCA);
-        lcd.write_data(0x85);
-        lcd.write_data(0x00);
-        lcd.write_data(0x78);
-        lcd.write_reg(Self::LCD_POWERA);
-        lcd.write_data(0x39);
-        lcd.write_data(0x2c);
-        lcd.write_data(0x00);
-        lcd.write_data(0x34);
-        lcd.write_data(0x02);
-        lcd.write_reg(Self::LCD_PRC);
-        lcd.write_data(0x20);
-        lcd.write_reg(Self::LCD_DTCB);
-        lcd.write_data(0x00);
-        lcd.write_data(0x00);
-        lcd.write_reg(Self::LCD_FRMCTR1);
-        lcd.write_data(0x00);
-        lcd.write_data(0x1b);
-        lcd.write_reg(Self::LCD_DFC);
-        lcd.write_data(0x0a);
-        lcd.write_data(0xa2);
-        lcd.write_reg(Self::LCD_POWER1);
-        lcd.write_data(0x10);
-        lcd.write_reg(Self::LCD_POWER2);
-        lcd.write_data(0x10);
-        lcd.write_reg(Self::LCD_VCOM1);
-        lcd.write_data(0x45);
-        lcd.write_data(0x15);
-        lcd.write_reg(Self::LCD_VCOM2);
-        lcd.write_data(0x90);
-        lcd.write_reg(Self::LCD_MAC);
-        lcd.write_data(0xc8);
-        lcd.write_reg(Self::LCD_3GAMMA_EN);
-        lcd.write_data(0x00);
-        lcd.write_reg(Self::LCD_RGB_INTERFACE);
-        lcd.write_data(0xc2);
-        lcd.write_reg(Self::LCD_DFC);
-        lcd.write_data(0x0a);
-        lcd.write_data(0xa7);
-        lcd.write_data(0x27);
-        lcd.write_data(0x04);
-
-        /* Colomn address set */
-        lcd.write_reg(Self::LCD_COLUMN_ADDR);
-        lcd.write_data(0x00);
-        lcd.write_data(0x00);
-        lcd.write_data(0x00);
-        lcd.write_data(0xef);
-        /* Page address set */
-        lcd.write_reg(Self::LCD_PAGE_ADDR);
-        lcd.write_data(0x00);
-        lcd.write_data(0x00);
-        lcd.write_data(0x01);
-        lcd.write_data(0x3f);
-        lcd.write_reg(Self::LCD_INTERFACE);
-        lcd.write_data(0x01);
-        lcd.write_data(0x00);
-        lcd.write_data(0x06);
-
-        lcd.write_reg(Self::LCD_GRAM);
-        // LCD_Delay(200);
-        for _ in 0..100_000 {
-        }
-
-        lcd.write_reg(Self::LCD_GAMMA);
-        lcd.write_data(0x01);
-
-        lcd.write_reg(Self::LCD_PGAMMA);
-        lcd.write_data(0x0f);
-        lcd.write_data(0x29);
-        lcd.write_data(0x24);
-        lcd.write_data(0x0c);
-        lcd.write_data(0x0e);
-        lcd.write_data(0x09);
-        lcd.write_data(0x4e);
-        lcd.write_data(0x78);
-        lcd.write_data(0x3c);
-        lcd.write_data(0x09);
-        lcd.write_data(0x13);
-        lcd.write_data(0x05);
-        lcd.write_data(0x17);
-        lcd.write_data(0x11);
-        lcd.write_data(0x00);
-        lcd.write_reg(Self::LCD_NGAMMA);
-        lcd.write_data(0x00);
-        lcd.write_data(0x16);
-        lcd.write_data(0x1b);
-        lcd.write_data(0x04);
-        lcd.write_data(0x11);
-        lcd.write_data(0x07);
-        lcd.write_data(0x31);
-        lcd.write_data(0x33);
-        lcd.write_data(0x42);
-        lcd.write_data(0x05);
-        lcd.write_data(0x0c);
-        lcd.write_data(0x0a);
-        lcd.write_data(0x28);
-        lcd.write_data(0x2f);
-        lcd.write_data(0x0f);
-
-        lcd.write_reg(Self::LCD_SLEEP_OUT);
-        // LCD_Delay(200);
-        for _ in 0..100_000 {
-        }
-
-        lcd.write_reg(Self::LCD_DISPLAY_ON);
+    /// Power-up sequence for the STM32F429 Discovery glass, RGB interface in DE mode
+    pub const INIT_SEQUENCE: &'static [InitCommand] = &[
+        // Undocumented, as sent by ST's BSP
+        InitCommand::new(0xca, &[0xc3, 0x08, 0x50]),
+        init_register!(PowerControlB { pceq: true, power_control: 0b00, dc_ena: true }),
+        init_register!(PowerOnSequence {
+            cp1_soft_start: 0b10,
+            cp23_soft_start: 0b00,
+            en_vcl: 0b00,
+            en_ddvdh: 0b11,
+            en_vgh: 0b01,
+            en_vgl: 0b10,
+            ddvdh_enh: true,
+        }),
+        init_register!(DriverTimingA { now: true, eq: false, cr: false, pc: 0b00 }),
+        init_register!(PowerControlA { reg_vd: 0b100, vbc: 0b010 }),
+        init_register!(PumpRatioControl { ratio: 2 }),
+        init_register!(DriverTimingB { t1: 0b00, t2: 0b00, t3: 0b00, t4: 0b00 }),
+        // 70 Hz with the datasheet porches, 00h 1Bh
+        init_register!(FrameRateNormal, PanelTiming::DATASHEET.frame_rate(70)),
+        // Only the first two parameters, the line count and divider follow below
+        InitCommand::new(Self::LCD_DFC, &const {
+            let [ptg_pt, scan, ..] = DisplayFunctionControl { isc: 0x02, ..RGB_DE.2 }.encode();
+            [ptg_pt, scan]
+        }),
+        init_register!(PowerControl1 { vrh: 0x10 }),
+        init_register!(PowerControl2 { sap: 0b001, bt: 0b000 }),
+        init_register!(VcomControl { vmh: 0x45, vml: 0x15 }),
+        init_register!(VcomOffset { nvm: true, vmf: 0x10 }),
+        init_register!(MemoryAccessControl {
+            my: true,
+            mx: true,
+            mv: false,
+            ml: false,
+            bgr: true,
+            mh: false,
+        }),
+        init_register!(Enable3Gamma { enable: false }),
+        init_register!(RgbInterfaceControl, RGB_DE.0),
+        init_register!(DisplayFunctionControl, RGB_DE.2),
+        init_register!(ColumnAddress { start: 0, end: Self::ILI9341_LCD_PIXEL_WIDTH as u16 - 1 }),
+        init_register!(PageAddress { start: 0, end: Self::ILI9341_LCD_PIXEL_HEIGHT as u16 - 1 }),
+        init_register!(InterfaceControl, RGB_DE.1),
+        InitCommand::new(Self::LCD_GRAM, &[]).delay(200),
+        InitCommand::new(Self::LCD_GAMMA, &[GammaCurve::ST_REFERENCE.gamma_set.curve]),
+        InitCommand::new(Self::LCD_PGAMMA, &GammaCurve::ST_REFERENCE.positive.params),
+        InitCommand::new(Self::LCD_NGAMMA, &GammaCurve::ST_REFERENCE.negative.params),
+        InitCommand::new(Self::LCD_SLEEP_OUT, &[]).delay(200),
+        InitCommand::new(Self::LCD_DISPLAY_ON, &[]),
         /* GRAM start writing */
-        lcd.write_reg(Self::LCD_GRAM);
+        InitCommand::new(Self::LCD_GRAM, &[]),
+    ];
+
+    pub fn init(lcd: I, delay: D, verify: Verify) -> Result<Self, Ili9341Error<I::Error>> {
+        Self::init_with_sequence(lcd, delay, Self::INIT_SEQUENCE, verify)
+    }
+
+    /// Bring the panel up with an application supplied sequence instead of
+    /// [`INIT_SEQUENCE`](Self::INIT_SEQUENCE), e.g. for glass with different VCOM or gamma
+    ///
+    /// `verify` selects how much is read back to confirm the panel is there and the
+    /// sequence took effect; see [`Verify`].
+    pub fn init_with_sequence(
+        lcd: I,
+        delay: D,
+        sequence: &[InitCommand],
+        verify: Verify
+    ) -> Result<Self, Ili9341Error<I::Error>> {
+        info!("Init ili9341 panel");
+
+        let mut ili9341 = Self {
+            lcd,
+            delay,
+            sleeping: true,
+            display_on: false,
+            partial: false,
+            idle: false,
+            madctl: MemoryAccessControl::decode([0x00]),
+            orientation: Orientation::PortraitFlipped,
+            mirror_x: false,
+            mirror_y: false,
+            scroll_area: VerticalScrollDefinition {
+                top_fixed: 0,
+                scroll: Self::ILI9341_LCD_PIXEL_HEIGHT as u16,
+                bottom_fixed: 0,
+            },
+            tearing: None,
+            pixel_format: PixelFormatSet::decode([0x66]),
+            interface_mode: InterfaceMode::Mcu,
+        };
+
+        if verify != Verify::None {
+            let id4 = ili9341.read_id4()?;
+            if id4.model != Id4::ILI9341 {
+                return Err(match (id4.version, id4.model) {
+                    (0x00, 0x0000) | (0xff, 0xffff) => Ili9341Error::NotConnected,
+                    _ => Ili9341Error::UnexpectedId(id4),
+                });
+            }
+        }
+
+        ili9341.run_sequence(sequence)?;
+
+        // Registers not touched by the sequence keep their reset values
+        let madctl = last_param(sequence, Self::LCD_MAC).unwrap_or(0x00);
+        let colmod = last_param(sequence, Self::LCD_PIXEL_FORMAT).unwrap_or(0x66);
+        let mode = if verify == Verify::Readback {
+            let [actual] = ili9341.read_madctl()?.encode();
+            verify_readback(Self::LCD_MAC, madctl, actual)?;
+            let [actual] = ili9341.read_pixel_format()?.encode();
+            verify_readback(Self::LCD_PIXEL_FORMAT, colmod, actual)?;
+
+            ili9341.read_power_mode()?
+        } else {
+            power_mode(sequence)
+        };
+
+        ili9341.madctl = MemoryAccessControl::decode([madctl]);
+        ili9341.pixel_format = PixelFormatSet::decode([colmod]);
+        ili9341.interface_mode = interface_mode(sequence);
+        (ili9341.orientation, ili9341.mirror_x, ili9341.mirror_y) = Orientation::from_madctl(
+            ili9341.madctl
+        );
+
+        ili9341.sleeping = !mode.sleep_out;
+        ili9341.display_on = mode.display_on;
+        ili9341.partial = mode.partial;
+        ili9341.idle = mode.idle;
+
+        Ok(ili9341)
+    }
+
+    /// Play back `sequence` command by command, honouring each post-delay
+    pub fn run_sequence(&mut self, sequence: &[InitCommand]) -> Result<(), Ili9341Error<I::Error>> {
+        for command in sequence {
+            self.write(command.cmd, command.params)?;
+            if command.delay_ms > 0 {
+                self.delay.delay_ms(command.delay_ms);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Validate `reg` and write it to the panel
+    pub fn write_register<R: Register>(&mut self, reg: &R) -> Result<(), Ili9341Error<I::Error>> {
+        reg.validate()?;
+
+        self.write(R::CMD, reg.encode().as_ref())
+    }
 
-        Self { lcd }
+    pub fn orientation(&self) -> Orientation {
+        self.orientation
     }
 
-    pub fn on(&mut self) {
-        self.lcd.write_reg(Self::LCD_DISPLAY_ON);
+    pub fn set_orientation(
+        &mut self,
+        orientation: Orientation
+    ) -> Result<(), Ili9341Error<I::Error>> {
+        self.orientation = orientation;
+        self.update_madctl()
     }
 
-    pub fn off(&mut self) {
-        self.lcd.write_reg(Self::LCD_DISPLAY_OFF);
+    /// Mirror the picture along the column (`mirror_x`) and/or page (`mirror_y`) address
+    pub fn set_mirror(
+        &mut self,
+        mirror_x: bool,
+        mirror_y: bool
+    ) -> Result<(), Ili9341Error<I::Error>> {
+        self.mirror_x = mirror_x;
+        self.mirror_y = mirror_y;
+        self.update_madctl()
+    }
+
+    pub fn set_color_order(&mut self, order: ColorOrder) -> Result<(), Ili9341Error<I::Error>> {
+        self.madctl.bgr = order == ColorOrder::Bgr;
+        self.update_madctl()
+    }
+
+    /// Logical width in pixels for the current orientation, as addressed by
+    /// [`set_window`](Self::set_window) over the MCU interface
+    ///
+    /// The RGB interface does not rotate; size the LTDC with
+    /// [`PanelTiming::ACTIVE_WIDTH`] instead.
+    pub fn width(&self) -> usize {
+        if self.madctl.mv { Self::ILI9341_LCD_PIXEL_HEIGHT } else { Self::ILI9341_LCD_PIXEL_WIDTH }
+    }
+
+    /// Logical height in pixels for the current orientation, see [`width`](Self::width)
+    pub fn height(&self) -> usize {
+        if self.madctl.mv { Self::ILI9341_LCD_PIXEL_WIDTH } else { Self::ILI9341_LCD_PIXEL_HEIGHT }
+    }
+
+    fn update_madctl(&mut self) -> Result<(), Ili9341Error<I::Error>> {
+        let (mv, mx, my) = self.orientation.madctl();
+        self.madctl.mv = mv;
+        self.madctl.mx = mx != self.mirror_x;
+        self.madctl.my = my != self.mirror_y;
+
+        let madctl = self.madctl;
+        self.write_register(&madctl)
+    }
+
+    /// Split the 320 GRAM lines into a fixed top area, a scrolling area and a fixed
+    /// bottom area for hardware vertical scrolling
+    pub fn define_scroll_area(
+        &mut self,
+        top_fixed: u16,
+        scroll: u16,
+        bottom_fixed: u16
+    ) -> Result<(), Ili9341Error<I::Error>> {
+        let area = VerticalScrollDefinition { top_fixed, scroll, bottom_fixed };
+        self.write_register(&area)?;
+        self.scroll_area = area;
+
+        Ok(())
+    }
+
+    pub fn scroll_area(&self) -> VerticalScrollDefinition {
+        self.scroll_area
+    }
+
+    /// Show GRAM line `line` at the top of the scrolling area
+    ///
+    /// `line` has to lie inside the scrolling area set with
+    /// [`define_scroll_area`](Self::define_scroll_area).
+    pub fn set_scroll_start(&mut self, line: u16) -> Result<(), Ili9341Error<I::Error>> {
+        let area = self.scroll_area;
+        if line < area.top_fixed || line >= area.top_fixed + area.scroll {
+            return Err(Ili9341Error::Range(RangeError { cmd: Self::LCD_VSCRSADD, field: "line" }));
+        }
+
+        self.write_register(&VerticalScrollStart { line })
+    }
+
+    pub fn interface_mode(&self) -> InterfaceMode {
+        self.interface_mode
+    }
+
+    /// Switch the pixel path between the RGB interface and GRAM writes over SPI
+    ///
+    /// The LTDC can keep running while the panel listens to SPI; its output is simply
+    /// ignored until an RGB mode is selected again.
+    pub fn set_interface_mode(
+        &mut self,
+        mode: InterfaceMode
+    ) -> Result<(), Ili9341Error<I::Error>> {
+        let (rgb, interface, dfc) = mode.registers();
+        if let InterfaceMode::RgbSync(porch) = &mode {
+            self.write_register(porch)?;
+        }
+        self.write_register(&rgb)?;
+        self.write_register(&dfc)?;
+        self.write_register(&interface)?;
+        self.interface_mode = mode;
+
+        Ok(())
+    }
+
+    /// Pixel formats of the RGB and MCU interfaces (3Ah)
+    ///
+    /// `dbi` decides how [`write_pixels`](Self::write_pixels) packs each pixel: two bytes
+    /// for 16 bits/pixel, three for 18 bits/pixel.
+    pub fn set_pixel_format(
+        &mut self,
+        format: PixelFormatSet
+    ) -> Result<(), Ili9341Error<I::Error>> {
+        self.write_register(&format)?;
+        self.pixel_format = format;
+
+        Ok(())
+    }
+
+    /// Program the porches and the internal frame rate from `timing`
+    ///
+    /// The porches take effect in RGB-SYNC and MCU mode and the frame rate in MCU mode;
+    /// in RGB-DE mode the LTDC alone sets the pace.
+    pub fn set_timing(
+        &mut self,
+        timing: &PanelTiming,
+        refresh_hz: u32
+    ) -> Result<(), Ili9341Error<I::Error>> {
+        self.write_register(&timing.porch()?)?;
+        self.write_register(&timing.frame_rate(refresh_hz))
+    }
+
+    /// Select a factory gamma curve and load its correction tables
+    pub fn set_gamma(&mut self, gamma: &GammaCurve) -> Result<(), Ili9341Error<I::Error>> {
+        gamma.gamma_set.validate()?;
+        gamma.positive.validate()?;
+        gamma.negative.validate()?;
+
+        self.write_register(&gamma.gamma_set)?;
+        self.write_register(&gamma.positive)?;
+        self.write_register(&gamma.negative)
+    }
+
+    /// Load the red/blue digital gamma lookup tables and switch them on
+    pub fn set_digital_gamma(
+        &mut self,
+        table1: &DigitalGamma1,
+        table2: &DigitalGamma2
+    ) -> Result<(), Ili9341Error<I::Error>> {
+        self.write_register(table1)?;
+        self.write_register(table2)?;
+        self.write_register(&Enable3Gamma { enable: true })
+    }
+
+    pub fn disable_digital_gamma(&mut self) -> Result<(), Ili9341Error<I::Error>> {
+        self.write_register(&Enable3Gamma { enable: false })
+    }
+
+    /// Restrict GRAM writes to the inclusive window `(x0, y0)`-`(x1, y1)`, in the
+    /// coordinates of the current orientation
+    pub fn set_window(
+        &mut self,
+        x0: u16,
+        y0: u16,
+        x1: u16,
+        y1: u16
+    ) -> Result<(), Ili9341Error<I::Error>> {
+        if x1 as usize >= self.width() {
+            let err = RangeError { cmd: Self::LCD_COLUMN_ADDR, field: "end" };
+            return Err(Ili9341Error::Range(err));
+        }
+        if y1 as usize >= self.height() {
+            let err = RangeError { cmd: Self::LCD_PAGE_ADDR, field: "end" };
+            return Err(Ili9341Error::Range(err));
+        }
+
+        self.write_register(&ColumnAddress { start: x0, end: x1 })?;
+        self.write_register(&PageAddress { start: y0, end: y1 })
+    }
+
+    /// Stream `pixels` into GRAM from the start of the current window, left to right and
+    /// top to bottom, wrapping back to the start once the window is full
+    pub fn write_pixels<P>(&mut self, pixels: P) -> Result<(), Ili9341Error<I::Error>>
+        where
+            P: IntoIterator<Item = Rgb565>
+    {
+        if self.interface_mode != InterfaceMode::Mcu {
+            return Err(Ili9341Error::RgbInterface);
+        }
+
+        self.lcd.write_reg(Self::LCD_GRAM).map_err(Ili9341Error::Interface)?;
+
+        // 48 bytes hold a whole number of pixels in either format
+        let mut buf = [0u8; 48];
+        let mut len = 0;
+        for pixel in pixels {
+            if self.pixel_format.dbi == 0b101 {
+                buf[len..len + 2].copy_from_slice(&pixel.0.to_be_bytes());
+                len += 2;
+            } else {
+                buf[len..len + 3].copy_from_slice(&pixel.to_rgb666());
+                len += 3;
+            }
+            if len == buf.len() {
+                self.lcd.write_data_slice(&buf).map_err(Ili9341Error::Interface)?;
+                len = 0;
+            }
+        }
+
+        self.lcd.write_data_slice(&buf[..len]).map_err(Ili9341Error::Interface)
     }
-}
\ No newline at end of file
+
+    /// Read `window` back from GRAM into `buf`, left to right and top to bottom
+    ///
+    /// Memory reads come back at 18 bits/pixel whatever the MCU pixel format; the 6-bit
+    /// red and blue components lose their low bit on the way to RGB565. A `buf` larger
+    /// than the window wraps around to its start again.
+    pub fn read_pixels(
+        &mut self,
+        window: Window,
+        buf: &mut [Rgb565]
+    ) -> Result<(), Ili9341Error<I::Error>> {
+        if self.interface_mode != InterfaceMode::Mcu {
+            return Err(Ili9341Error::RgbInterface);
+        }
+
+        self.set_window(window.x0, window.y0, window.x1, window.y1)?;
+
+        // Every read starts with a dummy byte, 16 pixels of 3 bytes follow it
+        let mut raw = [0u8; 1 + 16 * 3];
+        let mut reg = Self::LCD_RAMRD;
+        for chunk in buf.chunks_mut(16) {
+            let len = 1 + chunk.len() * 3;
+            self.lcd.read_data(reg, &mut raw[..len]).map_err(Ili9341Error::Interface)?;
+            for (pixel, rgb) in chunk.iter_mut().zip(raw[1..len].chunks_exact(3)) {
+                *pixel = Rgb565::new(rgb[0], rgb[1], rgb[2]);
+            }
+            reg = Self::LCD_READ_MEM_CONTINUE;
+        }
+
+        Ok(())
+    }
+
+    /// Fill a `width` x `height` rectangle with its top-left corner at `(x, y)`
+    pub fn fill_rect(
+        &mut self,
+        x: u16,
+        y: u16,
+        width: u16,
+        height: u16,
+        color: Rgb565
+    ) -> Result<(), Ili9341Error<I::Error>> {
+        if width == 0 || height == 0 {
+            return Ok(());
+        }
+
+        // A saturated corner is past the panel and rejected by `set_window`
+        let x1 = x.saturating_add(width - 1);
+        let y1 = y.saturating_add(height - 1);
+        self.set_window(x, y, x1, y1)?;
+        self.write_pixels(core::iter::repeat_n(color, width as usize * height as usize))
+    }
+
+    /// Switch the TE output on, pulsing as selected by `mode`
+    pub fn enable_tearing_effect(
+        &mut self,
+        mode: TearingMode
+    ) -> Result<(), Ili9341Error<I::Error>> {
+        self.write_register(&TearingEffectOn { vh_blanking: mode == TearingMode::VHBlank })?;
+        self.tearing = Some(mode);
+
+        Ok(())
+    }
+
+    pub fn disable_tearing_effect(&mut self) -> Result<(), Ili9341Error<I::Error>> {
+        self.write(Self::LCD_TEOFF, &[])?;
+        self.tearing = None;
+
+        Ok(())
+    }
+
+    /// Raise TE when the panel refresh reaches `line` instead of at the start of V-blanking
+    pub fn set_tear_scanline(&mut self, line: u16) -> Result<(), Ili9341Error<I::Error>> {
+        self.write_register(&TearScanline { line })
+    }
+
+    /// Wait for the next TE pulse and then run `update`, typically a GRAM write of the
+    /// region that changed, so it races ahead of the panel refresh instead of tearing
+    pub fn synchronized<T: TearSignal, R>(
+        &mut self,
+        te: &mut T,
+        update: impl FnOnce(&mut Self) -> Result<R, Ili9341Error<I::Error>>
+    ) -> Result<R, Ili9341Error<I::Error>> {
+        if self.tearing.is_none() {
+            return Err(Ili9341Error::TearingEffectOff);
+        }
+
+        te.wait_for_tear().map_err(|err| Ili9341Error::TearSignal(err.kind()))?;
+
+        update(self)
+    }
+
+    /// Only refresh rows `start_row..=end_row`, the rest of the panel shows the
+    /// non-display area colour selected by PTG/PT in Display Function Control
+    pub fn enter_partial_mode(
+        &mut self,
+        start_row: u16,
+        end_row: u16,
+        frame_rate: &FrameRatePartial
+    ) -> Result<(), Ili9341Error<I::Error>> {
+        let to = PowerState::awake(true, true, self.idle);
+        self.expect_state(&Self::DISPLAYING, to)?;
+
+        self.write_register(&PartialArea { start_row, end_row })?;
+        self.write_register(frame_rate)?;
+        self.write(Self::LCD_PTLON, &[])?;
+        self.partial = true;
+
+        Ok(())
+    }
+
+    /// Reduce the colour depth to 8 colours (MSB of each component) at its own frame rate
+    pub fn enter_idle_mode(
+        &mut self,
+        frame_rate: &FrameRateIdle
+    ) -> Result<(), Ili9341Error<I::Error>> {
+        let to = PowerState::awake(true, self.partial, true);
+        self.expect_state(&Self::DISPLAYING, to)?;
+
+        self.write_register(frame_rate)?;
+        self.write(Self::LCD_IDMON, &[])?;
+        self.idle = true;
+
+        Ok(())
+    }
+
+    /// Return to full colour, partial mode is kept
+    pub fn exit_idle_mode(&mut self) -> Result<(), Ili9341Error<I::Error>> {
+        let to = PowerState::awake(true, self.partial, false);
+        self.expect_state(&[PowerState::Idle, PowerState::PartialIdle], to)?;
+
+        self.write(Self::LCD_IDMOFF, &[])?;
+        self.idle = false;
+
+        Ok(())
+    }
+
+    /// Leave partial and scrolling mode and refresh the whole panel, idle mode is kept
+    pub fn normal_mode(&mut self) -> Result<(), Ili9341Error<I::Error>> {
+        let to = PowerState::awake(true, false, self.idle);
+        self.expect_state(&Self::DISPLAYING, to)?;
+
+        self.write(Self::LCD_NORMAL_MODE_ON, &[])?;
+        self.partial = false;
+
+        Ok(())
+    }
+
+    /// Let the brightness control block drive LEDPWM from the DBV register, fading
+    /// between levels when `dimming` is set
+    pub fn enable_brightness_control(
+        &mut self,
+        dimming: bool
+    ) -> Result<(), Ili9341Error<I::Error>> {
+        self.write_register(&ControlDisplay { bctrl: true, dd: dimming, bl: true })
+    }
+
+    pub fn disable_brightness_control(&mut self) -> Result<(), Ili9341Error<I::Error>> {
+        self.write_register(&ControlDisplay { bctrl: false, dd: false, bl: false })
+    }
+
+    /// Set the display brightness (51h), 0x00 is darkest and 0xff brightest
+    pub fn set_brightness(&mut self, brightness: u8) -> Result<(), Ili9341Error<I::Error>> {
+        self.write(Self::LCD_WDB, &[brightness])
+    }
+
+    pub fn set_cabc_mode(&mut self, mode: CabcMode) -> Result<(), Ili9341Error<I::Error>> {
+        self.write_register(&mode)
+    }
+
+    /// Lowest brightness CABC may dim down to (5Eh)
+    pub fn set_cabc_min_brightness(
+        &mut self,
+        brightness: u8
+    ) -> Result<(), Ili9341Error<I::Error>> {
+        self.write(Self::LCD_WRITE_CABC, &[brightness])
+    }
+
+    /// Configure the LEDPWM frequency and the LEDON/LEDPWM pin polarities, for panels
+    /// whose backlight driver is wired to the controller rather than to the MCU
+    pub fn configure_backlight(
+        &mut self,
+        pwm: &BacklightPwm,
+        pins: &BacklightPins
+    ) -> Result<(), Ili9341Error<I::Error>> {
+        self.write_register(pwm)?;
+        self.write_register(pins)
+    }
+
+    pub fn read_brightness(&mut self) -> Result<u8, Ili9341Error<I::Error>> {
+        let [brightness] = self.read_reg(Self::LCD_RDDISBV, Dummy::None)?;
+        Ok(brightness)
+    }
+
+    pub fn read_control_display(&mut self) -> Result<ControlDisplay, Ili9341Error<I::Error>> {
+        Ok(ControlDisplay::decode(self.read_reg(Self::LCD_RDCTRLD, Dummy::None)?))
+    }
+
+    pub fn read_cabc_mode(&mut self) -> Result<CabcMode, Ili9341Error<I::Error>> {
+        Ok(CabcMode::decode(self.read_reg(Self::LCD_RDCABC, Dummy::None)?))
+    }
+
+    pub fn read_cabc_min_brightness(&mut self) -> Result<u8, Ili9341Error<I::Error>> {
+        let [brightness] = self.read_reg(Self::LCD_READ_CABC, Dummy::None)?;
+        Ok(brightness)
+    }
+
+    /// Read display identification information (04h)
+    pub fn read_id(&mut self) -> Result<DisplayId, Ili9341Error<I::Error>> {
+        Ok(DisplayId::decode(self.read_reg(Self::LCD_READ_DISPLAY_ID, Dummy::Bit)?))
+    }
+
+    /// Read the IC version and model (D3h)
+    pub fn read_id4(&mut self) -> Result<Id4, Ili9341Error<I::Error>> {
+        Ok(Id4::decode(self.read_reg(Self::LCD_READ_ID4, Dummy::Byte)?))
+    }
+
+    pub fn read_status(&mut self) -> Result<DisplayStatus, Ili9341Error<I::Error>> {
+        Ok(DisplayStatus::decode(self.read_reg(Self::LCD_RDDST, Dummy::Bit)?))
+    }
+
+    pub fn read_power_mode(&mut self) -> Result<PowerMode, Ili9341Error<I::Error>> {
+        Ok(PowerMode::decode(self.read_reg(Self::LCD_RDDPM, Dummy::None)?))
+    }
+
+    pub fn read_madctl(&mut self) -> Result<MemoryAccessControl, Ili9341Error<I::Error>> {
+        Ok(MemoryAccessControl::decode(self.read_reg(Self::LCD_RDDMADCTL, Dummy::None)?))
+    }
+
+    pub fn read_pixel_format(&mut self) -> Result<PixelFormatSet, Ili9341Error<I::Error>> {
+        Ok(PixelFormatSet::decode(self.read_reg(Self::LCD_RDDCOLMOD, Dummy::None)?))
+    }
+
+    pub fn power_state(&self) -> PowerState {
+        if self.sleeping {
+            PowerState::Sleeping
+        } else {
+            self.awake_state()
+        }
+    }
+
+    /// Switch the display output on, partial and idle mode are kept
+    pub fn on(&mut self) -> Result<(), Ili9341Error<I::Error>> {
+        let to = PowerState::awake(true, self.partial, self.idle);
+        self.expect_state(&Self::AWAKE, to)?;
+
+        self.write(Self::LCD_DISPLAY_ON, &[])?;
+        self.display_on = true;
+
+        Ok(())
+    }
+
+    /// Blank the display output, partial and idle mode stay in effect for [`on`](Self::on)
+    pub fn off(&mut self) -> Result<(), Ili9341Error<I::Error>> {
+        self.expect_state(&Self::AWAKE, PowerState::Off)?;
+
+        self.write(Self::LCD_DISPLAY_OFF, &[])?;
+        self.display_on = false;
+
+        Ok(())
+    }
+
+    /// Enter sleep mode (10h), stopping the DC/DC converter and internal oscillator
+    pub fn sleep(&mut self) -> Result<(), Ili9341Error<I::Error>> {
+        self.expect_state(&Self::AWAKE, PowerState::Sleeping)?;
+
+        self.write(Self::LCD_SPLIN, &[])?;
+        // No command may follow Sleep In for 5 ms and Sleep Out not for 120 ms, wait
+        // out the longer one so wake() can be called straight away
+        self.delay.delay_ms(120);
+        self.sleeping = true;
+
+        Ok(())
+    }
+
+    /// Leave sleep mode (11h) and return to the state the panel was put to sleep from
+    pub fn wake(&mut self) -> Result<(), Ili9341Error<I::Error>> {
+        self.expect_state(&[PowerState::Sleeping], self.awake_state())?;
+
+        self.write(Self::LCD_SLEEP_OUT, &[])?;
+        // Supply voltages and clocks settle within 5 ms, but Sleep In is not allowed
+        // again until 120 ms after Sleep Out, so wait the full period here
+        self.delay.delay_ms(120);
+        self.sleeping = false;
+
+        Ok(())
+    }
+
+    pub fn release(self) -> (I, D) {
+        (self.lcd, self.delay)
+    }
+
+    /// State the panel is in while awake, and returns to on wake-up
+    fn awake_state(&self) -> PowerState {
+        PowerState::awake(self.display_on, self.partial, self.idle)
+    }
+
+    fn expect_state(
+        &self,
+        allowed: &[PowerState],
+        to: PowerState
+    ) -> Result<(), Ili9341Error<I::Error>> {
+        let from = self.power_state();
+        if !allowed.contains(&from) {
+            return Err(Ili9341Error::InvalidTransition { from, to });
+        }
+
+        Ok(())
+    }
+
+    fn write(&mut self, reg: u8, params: &[u8]) -> Result<(), Ili9341Error<I::Error>> {
+        self.lcd.write_reg(reg).map_err(Ili9341Error::Interface)?;
+        self.lcd.write_data_slice(params).map_err(Ili9341Error::Interface)
+    }
+
+    fn read_reg<const N: usize>(
+        &mut self,
+        reg: u8,
+        dummy: Dummy
+    ) -> Result<[u8; N], Ili9341Error<I::Error>> {
+        let mut raw = [0u8; 5];
+        let len = if dummy == Dummy::None { N } else { N + 1 };
+        self.lcd.read_data(reg, &mut raw[..len]).map_err(Ili9341Error::Interface)?;
+
+        let mut data = [0u8; N];
+        for (i, byte) in data.iter_mut().enumerate() {
+            *byte = match dummy {
+                Dummy::None => raw[i],
+                Dummy::Bit => (raw[i] << 1) | (raw[i + 1] >> 7),
+                Dummy::Byte => raw[i + 1],
+            };
+        }
+
+        Ok(data)
+    }
+}
+
+fn verify_readback<E>(reg: u8, expected: u8, actual: u8) -> Result<(), Ili9341Error<E>> {
+    if expected != actual {
+        return Err(Ili9341Error::Readback { reg, expected, actual });
+    }
+
+    Ok(())
+}
+
+/// Parameters of the last `reg` entry in `sequence`
+fn last_params(sequence: &[InitCommand], reg: u8) -> Option<&'static [u8]> {
+    sequence
+        .iter()
+        .rev()
+        .find(|command| command.cmd == reg)
+        .map(|command| command.params)
+}
+
+/// First parameter of the last `reg` entry in `sequence`
+fn last_param(sequence: &[InitCommand], reg: u8) -> Option<u8> {
+    last_params(sequence, reg).and_then(|params| params.first().copied())
+}
+
+/// Power mode `sequence` leaves a freshly reset panel in, as RDDPM would report it
+fn power_mode(sequence: &[InitCommand]) -> PowerMode {
+    type C = ILI9341<(), ()>;
+
+    // Sleeping, normal display mode, display off
+    let mut mode = PowerMode::decode([0x08]);
+    for command in sequence {
+        match command.cmd {
+            C::LCD_SLEEP_OUT => mode.sleep_out = true,
+            C::LCD_SPLIN => mode.sleep_out = false,
+            C::LCD_DISPLAY_ON => mode.display_on = true,
+            C::LCD_DISPLAY_OFF => mode.display_on = false,
+            C::LCD_PTLON => (mode.partial, mode.normal_mode) = (true, false),
+            C::LCD_NORMAL_MODE_ON => (mode.partial, mode.normal_mode) = (false, true),
+            C::LCD_IDMON => mode.idle = true,
+            C::LCD_IDMOFF => mode.idle = false,
+            _ => {}
+        }
+    }
+    mode.booster_on = mode.sleep_out;
+
+    mode
+}
+
+/// Interface mode `sequence` leaves the panel in, reset values filling in the gaps
+fn interface_mode(sequence: &[InitCommand]) -> InterfaceMode {
+    type C = ILI9341<(), ()>;
+
+    let rm = last_params(sequence, C::LCD_INTERFACE)
+        .and_then(|params| params.get(2).copied())
+        .is_some_and(|param| param & 0x02 != 0);
+    if !rm {
+        return InterfaceMode::Mcu;
+    }
+
+    let rcm = (last_param(sequence, C::LCD_RGB_INTERFACE).unwrap_or(0x40) >> 5) & 0b11;
+    if rcm != 0b11 {
+        return InterfaceMode::RgbDe;
+    }
+
+    let porch = match last_params(sequence, C::LCD_BPC) {
+        Some(&[vfp, vbp, hfp, hbp]) => [vfp, vbp, hfp, hbp],
+        _ => [0x02, 0x02, 0x0a, 0x14],
+    };
+    InterfaceMode::RgbSync(BlankingPorchControl::decode(porch))
+}
+
+#[cfg(test)]
+mod tests {
+    use core::{ convert::Infallible, iter };
+
+    use super::*;
+    use crate::drivers::ili9341_sim::NoDelay;
+
+    type Driver = ILI9341<RecordingInterface, NoDelay>;
+
+    /// Records every byte written, `true` marking a command, and answers the reads made
+    /// by `init` the way a freshly initialised panel does unless told otherwise
+    struct RecordingInterface {
+        writes: Vec<(bool, u8)>,
+        connected: bool,
+        /// ID4 as it comes off the wire, dummy parameter first
+        id4: [u8; 4],
+        madctl: u8,
+        colmod: u8,
+    }
+
+    impl RecordingInterface {
+        fn new() -> Self {
+            Self {
+                writes: Vec::new(),
+                connected: true,
+                id4: [0x00, 0x00, 0x93, 0x41],
+                madctl: 0xc8,
+                colmod: 0x66,
+            }
+        }
+
+        /// Nothing on the bus, every read returns zeros
+        fn disconnected() -> Self {
+            Self { connected: false, ..Self::new() }
+        }
+    }
+
+    impl Interface for RecordingInterface {
+        type Error = Infallible;
+
+        fn write_reg(&mut self, reg: u8) -> Result<(), Infallible> {
+            self.writes.push((true, reg));
+            Ok(())
+        }
+
+        fn write_data(&mut self, data: u8) -> Result<(), Infallible> {
+            self.writes.push((false, data));
+            Ok(())
+        }
+
+        fn read_data(&mut self, reg: u8, buf: &mut [u8]) -> Result<(), Infallible> {
+            let response: &[u8] = match reg {
+                _ if !self.connected => &[],
+                Driver::LCD_READ_ID4 => &self.id4,
+                Driver::LCD_RDDMADCTL => &[self.madctl],
+                Driver::LCD_RDDCOLMOD => &[self.colmod],
+                // Booster on, sleep out, normal mode, display on
+                Driver::LCD_RDDPM => &[0x9c],
+                _ => &[],
+            };
+            let len = response.len().min(buf.len());
+            buf.fill(0);
+            buf[..len].copy_from_slice(&response[..len]);
+
+            Ok(())
+        }
+    }
+
+    /// Bytes `sequence` puts on the wire
+    fn wire(sequence: &[InitCommand]) -> Vec<(bool, u8)> {
+        sequence
+            .iter()
+            .flat_map(|command| {
+                iter::once((true, command.cmd)).chain(command.params.iter().map(|&p| (false, p)))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn init_plays_back_the_sequence() {
+        let ili9341 = Driver::init(RecordingInterface::new(), NoDelay, Verify::Readback).unwrap();
+        assert_eq!(ili9341.power_state(), PowerState::On);
+        assert_eq!(ili9341.orientation(), Orientation::Portrait);
+        assert_eq!(ili9341.interface_mode(), InterfaceMode::RgbDe);
+
+        let (lcd, _) = ili9341.release();
+        assert_eq!(lcd.writes, wire(Driver::INIT_SEQUENCE));
+    }
+
+    /// `params` decode into valid fields that encode back unchanged
+    fn round_trips<R>(params: &[u8]) -> bool
+        where R: Register, R::Params: for<'a> TryFrom<&'a [u8]>
+    {
+        let Ok(raw) = R::Params::try_from(params) else {
+            return false;
+        };
+        let reg = R::decode(raw);
+        reg.validate().is_ok() && reg.encode().as_ref() == params
+    }
+
+    #[test]
+    fn init_sequence_round_trips() {
+        for command in Driver::INIT_SEQUENCE {
+            let params = command.params;
+            let ok = match command.cmd {
+                Driver::LCD_POWERB => round_trips::<PowerControlB>(params),
+                Driver::LCD_POWER_SEQ => round_trips::<PowerOnSequence>(params),
+                Driver::LCD_DTCA => round_trips::<DriverTimingA>(params),
+                Driver::LCD_POWERA => round_trips::<PowerControlA>(params),
+                Driver::LCD_PRC => round_trips::<PumpRatioControl>(params),
+                Driver::LCD_DTCB => round_trips::<DriverTimingB>(params),
+                Driver::LCD_FRMCTR1 => round_trips::<FrameRateNormal>(params),
+                // The short write leaves the last two parameters alone
+                Driver::LCD_DFC if params.len() == 2 => {
+                    let [_, _, nl, pcdiv] = RGB_DE.2.encode();
+                    round_trips::<DisplayFunctionControl>(&[params[0], params[1], nl, pcdiv])
+                }
+                Driver::LCD_DFC => round_trips::<DisplayFunctionControl>(params),
+                Driver::LCD_POWER1 => round_trips::<PowerControl1>(params),
+                Driver::LCD_POWER2 => round_trips::<PowerControl2>(params),
+                Driver::LCD_VCOM1 => round_trips::<VcomControl>(params),
+                Driver::LCD_VCOM2 => round_trips::<VcomOffset>(params),
+                Driver::LCD_MAC => round_trips::<MemoryAccessControl>(params),
+                Driver::LCD_3GAMMA_EN => round_trips::<Enable3Gamma>(params),
+                Driver::LCD_RGB_INTERFACE => round_trips::<RgbInterfaceControl>(params),
+                Driver::LCD_COLUMN_ADDR => round_trips::<ColumnAddress>(params),
+                Driver::LCD_PAGE_ADDR => round_trips::<PageAddress>(params),
+                Driver::LCD_INTERFACE => round_trips::<InterfaceControl>(params),
+                Driver::LCD_GAMMA => round_trips::<GammaSet>(params),
+                Driver::LCD_PGAMMA => round_trips::<PositiveGamma>(params),
+                Driver::LCD_NGAMMA => round_trips::<NegativeGamma>(params),
+                // Undocumented (CAh) and parameterless commands
+                _ => continue,
+            };
+            assert!(ok, "{:#04x} {:02x?} does not round-trip", command.cmd, params);
+        }
+
+        // Decoded fields, not just bytes: RGB-DE through GRAM, 320 lines, DOTCLK / 5
+        let rgb = last_params(Driver::INIT_SEQUENCE, Driver::LCD_RGB_INTERFACE).unwrap();
+        let rgb = RgbInterfaceControl::decode(rgb.try_into().unwrap());
+        assert!(rgb.bypass_memory && rgb.rcm == 0b10 && rgb.dpl);
+        let dfc = last_params(Driver::INIT_SEQUENCE, Driver::LCD_DFC).unwrap();
+        let dfc = DisplayFunctionControl::decode(dfc.try_into().unwrap());
+        assert!((dfc.nl as usize + 1) * 8 == Driver::ILI9341_LCD_PIXEL_HEIGHT && dfc.pcdiv == 4);
+    }
+
+    #[test]
+    fn init_without_panel() {
+        let result = Driver::init(RecordingInterface::disconnected(), NoDelay, Verify::Id);
+        assert!(matches!(result, Err(Ili9341Error::NotConnected)));
+    }
+
+    #[test]
+    fn init_unverified() {
+        // Nothing is read, so a board without the read line comes up all the same
+        let lcd = RecordingInterface::disconnected();
+        let ili9341 = Driver::init(lcd, NoDelay, Verify::None).unwrap();
+        assert_eq!(ili9341.power_state(), PowerState::On);
+        assert_eq!(ili9341.orientation(), Orientation::Portrait);
+
+        let (lcd, _) = ili9341.release();
+        assert_eq!(lcd.writes, wire(Driver::INIT_SEQUENCE));
+    }
+
+    #[test]
+    fn init_unexpected_id() {
+        // An ILI9340 answering
+        let lcd = RecordingInterface { id4: [0x00, 0x01, 0x93, 0x40], ..RecordingInterface::new() };
+        let result = Driver::init(lcd, NoDelay, Verify::Id);
+        let Err(Ili9341Error::UnexpectedId(id4)) = result else {
+            panic!("ID4 not rejected");
+        };
+        assert_eq!((id4.version, id4.model), (0x01, 0x9340));
+    }
+
+    #[test]
+    fn init_readback_mismatch() {
+        let lcd = RecordingInterface { madctl: 0x00, ..RecordingInterface::new() };
+        let result = Driver::init(lcd, NoDelay, Verify::Readback);
+        assert!(matches!(
+            result,
+            Err(Ili9341Error::Readback { reg: 0x36, expected: 0xc8, actual: 0x00 })
+        ));
+
+        let lcd = RecordingInterface { colmod: 0x55, ..RecordingInterface::new() };
+        let result = Driver::init(lcd, NoDelay, Verify::Readback);
+        assert!(matches!(
+            result,
+            Err(Ili9341Error::Readback { reg: 0x3a, expected: 0x66, actual: 0x55 })
+        ));
+
+        // Identifying alone does not read the registers back
+        let lcd = RecordingInterface { madctl: 0x00, ..RecordingInterface::new() };
+        assert!(Driver::init(lcd, NoDelay, Verify::Id).is_ok());
+    }
+
+    #[test]
+    fn on_off() {
+        let lcd = RecordingInterface::new();
+        let mut ili9341 = Driver::init(lcd, NoDelay, Verify::Readback).unwrap();
+        let init_len = wire(Driver::INIT_SEQUENCE).len();
+
+        ili9341.off().unwrap();
+        assert_eq!(ili9341.power_state(), PowerState::Off);
+        ili9341.on().unwrap();
+        assert_eq!(ili9341.power_state(), PowerState::On);
+
+        let (lcd, _) = ili9341.release();
+        assert_eq!(
+            lcd.writes[init_len..],
+            [(true, Driver::LCD_DISPLAY_OFF), (true, Driver::LCD_DISPLAY_ON)]
+        );
+    }
+
+    /// Adds up the time waited
+    struct Elapsed(u64);
+
+    impl DelayNs for Elapsed {
+        fn delay_ns(&mut self, ns: u32) {
+            self.0 += ns as u64;
+        }
+    }
+
+    #[test]
+    fn sleep_waits_before_sleep_out() {
+        let lcd = RecordingInterface::new();
+        let mut ili9341 = ILI9341::init(lcd, Elapsed(0), Verify::Readback).unwrap();
+        ili9341.delay.0 = 0;
+
+        ili9341.sleep().unwrap();
+        // Sleep Out may only follow Sleep In after 120 ms
+        assert!(ili9341.delay.0 >= 120_000_000);
+        ili9341.wake().unwrap();
+        assert_eq!(ili9341.power_state(), PowerState::On);
+    }
+
+    #[test]
+    fn porch_range() {
+        let field = |timing: PanelTiming| timing.porch().map_err(|err| err.field);
+
+        // 260 would wrap to 4, a valid porch, if narrowed before the check
+        assert_eq!(field(PanelTiming { hbp: 260, ..PanelTiming::DATASHEET }), Err("hbp"));
+        assert_eq!(field(PanelTiming { hfp: 0x20, ..PanelTiming::DATASHEET }), Err("hfp"));
+        assert_eq!(field(PanelTiming { vbp: 0x80, ..PanelTiming::DATASHEET }), Err("vbp"));
+        assert_eq!(field(PanelTiming { vfp: 1, ..PanelTiming::DATASHEET }), Err("vfp"));
+
+        let porch = PanelTiming::DATASHEET.porch().unwrap();
+        assert_eq!(porch, BlankingPorchControl { vfp: 4, vbp: 2, hfp: 10, hbp: 20 });
+    }
+
+    #[test]
+    fn init_frame_rate() {
+        let params = last_params(Driver::INIT_SEQUENCE, Driver::LCD_FRMCTR1).unwrap();
+        assert_eq!(params, [0x00, 0x1b]);
+
+        let frame_rate = FrameRateNormal::decode([params[0], params[1]]);
+        assert_eq!(PanelTiming::DATASHEET.internal_refresh_rate(&frame_rate), 70);
+    }
+}
@@ -0,0 +1,1579 @@
+#![allow(unused)]
+
+//! Typed parameters for the ILI9341 level 2 and extended command set
+//!
+//! Every register is a plain struct whose fields mirror the datasheet bit fields.
+//! `encode` packs them into the exact parameter bytes (fixed bits included),
+//! `decode` unpacks raw bytes and `validate` checks every field against its width
+//! and the ranges the datasheet marks as valid.
+
+use super::ili9341::{ GammaCurve, ILI9341 };
+
+type C = ILI9341<(), ()>;
+
+/// A field is out of range for its register
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RangeError {
+    pub cmd: u8,
+    pub field: &'static str,
+}
+
+impl RangeError {
+    const fn new(cmd: u8, field: &'static str) -> Self {
+        Self { cmd, field }
+    }
+}
+
+/// A command with typed parameters
+pub trait Register: Sized {
+    const CMD: u8;
+    type Params: AsRef<[u8]>;
+
+    fn validate(&self) -> Result<(), RangeError>;
+    fn encode(&self) -> Self::Params;
+    fn decode(params: Self::Params) -> Self;
+}
+
+const fn bit(value: bool, pos: u8) -> u8 {
+    (value as u8) << pos
+}
+
+const fn is_set(byte: u8, pos: u8) -> bool {
+    byte & (1 << pos) != 0
+}
+
+/// RGB Interface Signal Control (B0h)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RgbInterfaceControl {
+    /// Route RGB data through GRAM instead of directly to the shift register
+    pub bypass_memory: bool,
+    /// RGB interface selection, 0b10 = DE mode, 0b11 = SYNC mode
+    pub rcm: u8,
+    /// VSYNC polarity, set for high level sync clock
+    pub vspl: bool,
+    /// HSYNC polarity, set for high level sync clock
+    pub hspl: bool,
+    /// DOTCLK polarity, set to fetch data on the falling edge
+    pub dpl: bool,
+    /// ENABLE polarity, set for low enable
+    pub epl: bool,
+}
+
+impl RgbInterfaceControl {
+    pub const fn validate(&self) -> Result<(), RangeError> {
+        if self.rcm > 0b11 {
+            return Err(RangeError::new(C::LCD_RGB_INTERFACE, "rcm"));
+        }
+        Ok(())
+    }
+
+    pub const fn encode(&self) -> [u8; 1] {
+        [
+            bit(self.bypass_memory, 7) |
+                ((self.rcm & 0b11) << 5) |
+                bit(self.vspl, 3) |
+                bit(self.hspl, 2) |
+                bit(self.dpl, 1) |
+                bit(self.epl, 0),
+        ]
+    }
+
+    pub const fn decode(p: [u8; 1]) -> Self {
+        Self {
+            bypass_memory: is_set(p[0], 7),
+            rcm: (p[0] >> 5) & 0b11,
+            vspl: is_set(p[0], 3),
+            hspl: is_set(p[0], 2),
+            dpl: is_set(p[0], 1),
+            epl: is_set(p[0], 0),
+        }
+    }
+}
+
+/// Frame Rate Control (B1h normal, B2h idle, B3h partial mode)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FrameRateControl<const CMD: u8> {
+    /// Internal clock division ratio, fosc / 2^div
+    pub div: u8,
+    /// Clocks per line, 0x10 (16 clocks) to 0x1f (31 clocks)
+    pub rtn: u8,
+}
+
+pub type FrameRateNormal = FrameRateControl<{ C::LCD_FRMCTR1 }>;
+pub type FrameRateIdle = FrameRateControl<{ C::LCD_FRMCTR2 }>;
+pub type FrameRatePartial = FrameRateControl<{ C::LCD_FRMCTR3 }>;
+
+impl<const CMD: u8> FrameRateControl<CMD> {
+    pub const fn validate(&self) -> Result<(), RangeError> {
+        if self.div > 0b11 {
+            return Err(RangeError::new(CMD, "div"));
+        }
+        if self.rtn < 0x10 || self.rtn > 0x1f {
+            return Err(RangeError::new(CMD, "rtn"));
+        }
+        Ok(())
+    }
+
+    pub const fn encode(&self) -> [u8; 2] {
+        [self.div & 0b11, self.rtn & 0x1f]
+    }
+
+    pub const fn decode(p: [u8; 2]) -> Self {
+        Self { div: p[0] & 0b11, rtn: p[1] & 0x1f }
+    }
+}
+
+/// Display Inversion Control (B4h), set for frame inversion, clear for line inversion
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InversionControl {
+    pub normal: bool,
+    pub idle: bool,
+    pub partial: bool,
+}
+
+impl InversionControl {
+    pub const fn validate(&self) -> Result<(), RangeError> {
+        Ok(())
+    }
+
+    pub const fn encode(&self) -> [u8; 1] {
+        [bit(self.normal, 2) | bit(self.idle, 1) | bit(self.partial, 0)]
+    }
+
+    pub const fn decode(p: [u8; 1]) -> Self {
+        Self { normal: is_set(p[0], 2), idle: is_set(p[0], 1), partial: is_set(p[0], 0) }
+    }
+}
+
+/// Blanking Porch Control (B5h), in lines and DOTCLK cycles
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BlankingPorchControl {
+    pub vfp: u8,
+    pub vbp: u8,
+    pub hfp: u8,
+    pub hbp: u8,
+}
+
+impl BlankingPorchControl {
+    pub const fn validate(&self) -> Result<(), RangeError> {
+        if self.vfp < 2 || self.vfp > 0x7f {
+            return Err(RangeError::new(C::LCD_BPC, "vfp"));
+        }
+        if self.vbp < 2 || self.vbp > 0x7f {
+            return Err(RangeError::new(C::LCD_BPC, "vbp"));
+        }
+        if self.hfp < 2 || self.hfp > 0x1f {
+            return Err(RangeError::new(C::LCD_BPC, "hfp"));
+        }
+        if self.hbp < 2 || self.hbp > 0x1f {
+            return Err(RangeError::new(C::LCD_BPC, "hbp"));
+        }
+        Ok(())
+    }
+
+    pub const fn encode(&self) -> [u8; 4] {
+        [self.vfp & 0x7f, self.vbp & 0x7f, self.hfp & 0x1f, self.hbp & 0x1f]
+    }
+
+    pub const fn decode(p: [u8; 4]) -> Self {
+        Self { vfp: p[0] & 0x7f, vbp: p[1] & 0x7f, hfp: p[2] & 0x1f, hbp: p[3] & 0x1f }
+    }
+}
+
+/// Display Function Control (B6h)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DisplayFunctionControl {
+    /// Scan mode in non-display area
+    pub ptg: u8,
+    /// Source/VCOM output in non-display area
+    pub pt: u8,
+    /// Liquid crystal type, set for normally white
+    pub rev: bool,
+    /// Gate output scan direction
+    pub gs: bool,
+    /// Source output scan direction
+    pub ss: bool,
+    /// Gate driver pin arrangement
+    pub sm: bool,
+    /// Scan cycle interval of the gate driver in non-display area
+    pub isc: u8,
+    /// Number of lines to drive, (nl + 1) * 8
+    pub nl: u8,
+    /// External fosc divider, DOTCLK / (pcdiv + 1)
+    pub pcdiv: u8,
+}
+
+impl DisplayFunctionControl {
+    pub const fn validate(&self) -> Result<(), RangeError> {
+        if self.ptg > 0b11 {
+            return Err(RangeError::new(C::LCD_DFC, "ptg"));
+        }
+        if self.pt > 0b11 {
+            return Err(RangeError::new(C::LCD_DFC, "pt"));
+        }
+        if self.isc > 0x0f {
+            return Err(RangeError::new(C::LCD_DFC, "isc"));
+        }
+        if self.nl > 0x27 {
+            return Err(RangeError::new(C::LCD_DFC, "nl"));
+        }
+        if self.pcdiv > 0x3f {
+            return Err(RangeError::new(C::LCD_DFC, "pcdiv"));
+        }
+        Ok(())
+    }
+
+    pub const fn encode(&self) -> [u8; 4] {
+        [
+            ((self.ptg & 0b11) << 2) | (self.pt & 0b11),
+            bit(self.rev, 7) |
+                bit(self.gs, 6) |
+                bit(self.ss, 5) |
+                bit(self.sm, 4) |
+                (self.isc & 0x0f),
+            self.nl & 0x3f,
+            self.pcdiv & 0x3f,
+        ]
+    }
+
+    pub const fn decode(p: [u8; 4]) -> Self {
+        Self {
+            ptg: (p[0] >> 2) & 0b11,
+            pt: p[0] & 0b11,
+            rev: is_set(p[1], 7),
+            gs: is_set(p[1], 6),
+            ss: is_set(p[1], 5),
+            sm: is_set(p[1], 4),
+            isc: p[1] & 0x0f,
+            nl: p[2] & 0x3f,
+            pcdiv: p[3] & 0x3f,
+        }
+    }
+}
+
+/// Entry Mode Set (B7h)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EntryMode {
+    /// Gate output control
+    pub gon: bool,
+    pub dte: bool,
+    /// Low voltage detection, set to disable
+    pub gas: bool,
+}
+
+impl EntryMode {
+    pub const fn validate(&self) -> Result<(), RangeError> {
+        Ok(())
+    }
+
+    pub const fn encode(&self) -> [u8; 1] {
+        [bit(self.gon, 2) | bit(self.dte, 1) | bit(self.gas, 0)]
+    }
+
+    pub const fn decode(p: [u8; 1]) -> Self {
+        Self { gon: is_set(p[0], 2), dte: is_set(p[0], 1), gas: is_set(p[0], 0) }
+    }
+}
+
+/// Power Control 1 (C0h)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PowerControl1 {
+    /// GVDD level, 0x03 (3.00 V) to 0x3f (6.00 V)
+    pub vrh: u8,
+}
+
+impl PowerControl1 {
+    pub const fn validate(&self) -> Result<(), RangeError> {
+        if self.vrh < 0x03 || self.vrh > 0x3f {
+            return Err(RangeError::new(C::LCD_POWER1, "vrh"));
+        }
+        Ok(())
+    }
+
+    pub const fn encode(&self) -> [u8; 1] {
+        [self.vrh & 0x3f]
+    }
+
+    pub const fn decode(p: [u8; 1]) -> Self {
+        Self { vrh: p[0] & 0x3f }
+    }
+}
+
+/// Power Control 2 (C1h)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PowerControl2 {
+    /// Source driver bias current
+    pub sap: u8,
+    /// Step-up factor of the VGH/VGL generating circuits
+    pub bt: u8,
+}
+
+impl PowerControl2 {
+    pub const fn validate(&self) -> Result<(), RangeError> {
+        if self.sap > 0b111 {
+            return Err(RangeError::new(C::LCD_POWER2, "sap"));
+        }
+        if self.bt > 0b011 {
+            return Err(RangeError::new(C::LCD_POWER2, "bt"));
+        }
+        Ok(())
+    }
+
+    pub const fn encode(&self) -> [u8; 1] {
+        [((self.sap & 0b111) << 4) | (self.bt & 0x0f)]
+    }
+
+    pub const fn decode(p: [u8; 1]) -> Self {
+        Self { sap: (p[0] >> 4) & 0b111, bt: p[0] & 0x0f }
+    }
+}
+
+/// VCOM Control 1 (C5h)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VcomControl {
+    /// VCOMH level, 0x00 (2.700 V) to 0x64 (5.000 V)
+    pub vmh: u8,
+    /// VCOML level, 0x00 (-2.500 V) to 0x64 (0.000 V)
+    pub vml: u8,
+}
+
+impl VcomControl {
+    pub const fn validate(&self) -> Result<(), RangeError> {
+        if self.vmh > 0x64 {
+            return Err(RangeError::new(C::LCD_VCOM1, "vmh"));
+        }
+        if self.vml > 0x64 {
+            return Err(RangeError::new(C::LCD_VCOM1, "vml"));
+        }
+        Ok(())
+    }
+
+    pub const fn encode(&self) -> [u8; 2] {
+        [self.vmh & 0x7f, self.vml & 0x7f]
+    }
+
+    pub const fn decode(p: [u8; 2]) -> Self {
+        Self { vmh: p[0] & 0x7f, vml: p[1] & 0x7f }
+    }
+}
+
+/// VCOM Control 2 (C7h)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VcomOffset {
+    /// Use `vmf` instead of the NV memory value
+    pub nvm: bool,
+    /// VCOM offset, 0x00 (VMH - 63) to 0x7f (VMH + 63), 0x40 = no offset
+    pub vmf: u8,
+}
+
+impl VcomOffset {
+    pub const fn validate(&self) -> Result<(), RangeError> {
+        if self.vmf > 0x7f {
+            return Err(RangeError::new(C::LCD_VCOM2, "vmf"));
+        }
+        Ok(())
+    }
+
+    pub const fn encode(&self) -> [u8; 1] {
+        [bit(self.nvm, 7) | (self.vmf & 0x7f)]
+    }
+
+    pub const fn decode(p: [u8; 1]) -> Self {
+        Self { nvm: is_set(p[0], 7), vmf: p[0] & 0x7f }
+    }
+}
+
+/// Interface Control (F6h)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InterfaceControl {
+    pub my_eor: bool,
+    pub mx_eor: bool,
+    pub mv_eor: bool,
+    pub bgr_eor: bool,
+    /// Wrap to the window start instead of ignoring data past the window end
+    pub wemode: bool,
+    /// 65K colour to 262K colour expansion
+    pub epf: u8,
+    /// Transfer method for 16/18 bit data
+    pub mdt: u8,
+    /// Little endian for the 16 bit 65K MCU interface
+    pub endian: bool,
+    /// Display operation mode, 0b00 internal clock, 0b01 RGB, 0b10 VSYNC
+    pub dm: u8,
+    /// GRAM access interface, set for RGB interface
+    pub rm: bool,
+    /// RGB interface mode, set for 6 bit (1 transfer/pixel as 3 transfers)
+    pub rim: bool,
+}
+
+impl InterfaceControl {
+    pub const fn validate(&self) -> Result<(), RangeError> {
+        if self.epf > 0b11 {
+            return Err(RangeError::new(C::LCD_INTERFACE, "epf"));
+        }
+        if self.mdt > 0b11 {
+            return Err(RangeError::new(C::LCD_INTERFACE, "mdt"));
+        }
+        if self.dm > 0b10 {
+            return Err(RangeError::new(C::LCD_INTERFACE, "dm"));
+        }
+        Ok(())
+    }
+
+    pub const fn encode(&self) -> [u8; 3] {
+        [
+            bit(self.my_eor, 7) |
+                bit(self.mx_eor, 6) |
+                bit(self.mv_eor, 5) |
+                bit(self.bgr_eor, 3) |
+                bit(self.wemode, 0),
+            ((self.epf & 0b11) << 4) | (self.mdt & 0b11),
+            bit(self.endian, 5) | ((self.dm & 0b11) << 2) | bit(self.rm, 1) | bit(self.rim, 0),
+        ]
+    }
+
+    pub const fn decode(p: [u8; 3]) -> Self {
+        Self {
+            my_eor: is_set(p[0], 7),
+            mx_eor: is_set(p[0], 6),
+            mv_eor: is_set(p[0], 5),
+            bgr_eor: is_set(p[0], 3),
+            wemode: is_set(p[0], 0),
+            epf: (p[1] >> 4) & 0b11,
+            mdt: p[1] & 0b11,
+            endian: is_set(p[2], 5),
+            dm: (p[2] >> 2) & 0b11,
+            rm: is_set(p[2], 1),
+            rim: is_set(p[2], 0),
+        }
+    }
+}
+
+/// Power control A (CBh)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PowerControlA {
+    /// Vcore control, 0b000 (1.55 V) to 0b101 (1.70 V)
+    pub reg_vd: u8,
+    /// DDVDH control, 0b000 (5.8 V) to 0b110 (5.2 V)
+    pub vbc: u8,
+}
+
+impl PowerControlA {
+    pub const fn validate(&self) -> Result<(), RangeError> {
+        if self.reg_vd > 0b101 {
+            return Err(RangeError::new(C::LCD_POWERA, "reg_vd"));
+        }
+        if self.vbc > 0b110 {
+            return Err(RangeError::new(C::LCD_POWERA, "vbc"));
+        }
+        Ok(())
+    }
+
+    pub const fn encode(&self) -> [u8; 5] {
+        [0x39, 0x2c, 0x00, 0x30 | (self.reg_vd & 0b111), self.vbc & 0b111]
+    }
+
+    pub const fn decode(p: [u8; 5]) -> Self {
+        Self { reg_vd: p[3] & 0b111, vbc: p[4] & 0b111 }
+    }
+}
+
+/// Power control B (CFh)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PowerControlB {
+    /// Power saving by equalizing the source output
+    pub pceq: bool,
+    pub power_control: u8,
+    /// ESD protection
+    pub dc_ena: bool,
+}
+
+impl PowerControlB {
+    pub const fn validate(&self) -> Result<(), RangeError> {
+        if self.power_control > 0b11 {
+            return Err(RangeError::new(C::LCD_POWERB, "power_control"));
+        }
+        Ok(())
+    }
+
+    pub const fn encode(&self) -> [u8; 3] {
+        [
+            0x00,
+            0x81 | bit(self.pceq, 6) | ((self.power_control & 0b11) << 3),
+            0x20 | bit(self.dc_ena, 4),
+        ]
+    }
+
+    pub const fn decode(p: [u8; 3]) -> Self {
+        Self { pceq: is_set(p[1], 6), power_control: (p[1] >> 3) & 0b11, dc_ena: is_set(p[2], 4) }
+    }
+}
+
+/// Driver timing control A (E8h)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DriverTimingA {
+    /// Gate driver non-overlap timing
+    pub now: bool,
+    /// EQ timing
+    pub eq: bool,
+    /// CR timing
+    pub cr: bool,
+    /// Pre-charge timing
+    pub pc: u8,
+}
+
+impl DriverTimingA {
+    pub const fn validate(&self) -> Result<(), RangeError> {
+        if self.pc > 0b10 {
+            return Err(RangeError::new(C::LCD_DTCA, "pc"));
+        }
+        Ok(())
+    }
+
+    pub const fn encode(&self) -> [u8; 3] {
+        [0x84 | bit(self.now, 0), bit(self.eq, 4) | bit(self.cr, 0), 0x78 | (self.pc & 0b11)]
+    }
+
+    pub const fn decode(p: [u8; 3]) -> Self {
+        Self { now: is_set(p[0], 0), eq: is_set(p[1], 4), cr: is_set(p[1], 0), pc: p[2] & 0b11 }
+    }
+}
+
+/// Driver timing control B (EAh), gate driver timing VG_SW_T1..T4
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DriverTimingB {
+    pub t1: u8,
+    pub t2: u8,
+    pub t3: u8,
+    pub t4: u8,
+}
+
+impl DriverTimingB {
+    pub const fn validate(&self) -> Result<(), RangeError> {
+        if self.t1 > 0b11 || self.t2 > 0b11 || self.t3 > 0b11 || self.t4 > 0b11 {
+            return Err(RangeError::new(C::LCD_DTCB, "vg_sw_t"));
+        }
+        Ok(())
+    }
+
+    pub const fn encode(&self) -> [u8; 2] {
+        [
+            ((self.t4 & 0b11) << 6) |
+                ((self.t3 & 0b11) << 4) |
+                ((self.t2 & 0b11) << 2) |
+                (self.t1 & 0b11),
+            0x00,
+        ]
+    }
+
+    pub const fn decode(p: [u8; 2]) -> Self {
+        Self {
+            t1: p[0] & 0b11,
+            t2: (p[0] >> 2) & 0b11,
+            t3: (p[0] >> 4) & 0b11,
+            t4: (p[0] >> 6) & 0b11,
+        }
+    }
+}
+
+/// Power on sequence control (EDh)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PowerOnSequence {
+    /// Soft start keep frames for CP1
+    pub cp1_soft_start: u8,
+    /// Soft start keep frames for CP23
+    pub cp23_soft_start: u8,
+    /// Power on frame for VCL, DDVDH, VGH and VGL
+    pub en_vcl: u8,
+    pub en_ddvdh: u8,
+    pub en_vgh: u8,
+    pub en_vgl: u8,
+    /// DDVDH enhance mode
+    pub ddvdh_enh: bool,
+}
+
+impl PowerOnSequence {
+    pub const fn validate(&self) -> Result<(), RangeError> {
+        if self.cp1_soft_start > 0b11 || self.cp23_soft_start > 0b11 {
+            return Err(RangeError::new(C::LCD_POWER_SEQ, "soft_start"));
+        }
+        if self.en_vcl > 0b11 || self.en_ddvdh > 0b11 || self.en_vgh > 0b11 || self.en_vgl > 0b11 {
+            return Err(RangeError::new(C::LCD_POWER_SEQ, "power_on_frame"));
+        }
+        Ok(())
+    }
+
+    pub const fn encode(&self) -> [u8; 4] {
+        [
+            0x44 | ((self.cp1_soft_start & 0b11) << 4) | (self.cp23_soft_start & 0b11),
+            ((self.en_vcl & 0b11) << 4) | (self.en_ddvdh & 0b11),
+            ((self.en_vgh & 0b11) << 4) | (self.en_vgl & 0b11),
+            0x01 | bit(self.ddvdh_enh, 7),
+        ]
+    }
+
+    pub const fn decode(p: [u8; 4]) -> Self {
+        Self {
+            cp1_soft_start: (p[0] >> 4) & 0b11,
+            cp23_soft_start: p[0] & 0b11,
+            en_vcl: (p[1] >> 4) & 0b11,
+            en_ddvdh: p[1] & 0b11,
+            en_vgh: (p[2] >> 4) & 0b11,
+            en_vgl: p[2] & 0b11,
+            ddvdh_enh: is_set(p[3], 7),
+        }
+    }
+}
+
+/// Enable 3G (F2h), digital gamma control
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Enable3Gamma {
+    pub enable: bool,
+}
+
+impl Enable3Gamma {
+    pub const fn validate(&self) -> Result<(), RangeError> {
+        Ok(())
+    }
+
+    pub const fn encode(&self) -> [u8; 1] {
+        [bit(self.enable, 0)]
+    }
+
+    pub const fn decode(p: [u8; 1]) -> Self {
+        Self { enable: is_set(p[0], 0) }
+    }
+}
+
+/// Pump ratio control (F7h), DDVDH = ratio x VCI
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PumpRatioControl {
+    pub ratio: u8,
+}
+
+impl PumpRatioControl {
+    pub const fn validate(&self) -> Result<(), RangeError> {
+        if self.ratio < 2 || self.ratio > 3 {
+            return Err(RangeError::new(C::LCD_PRC, "ratio"));
+        }
+        Ok(())
+    }
+
+    pub const fn encode(&self) -> [u8; 1] {
+        [(self.ratio & 0b11) << 4]
+    }
+
+    pub const fn decode(p: [u8; 1]) -> Self {
+        Self { ratio: (p[0] >> 4) & 0b11 }
+    }
+}
+
+/// Gamma Set (26h), one of the four factory gamma curves
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GammaSet {
+    /// Curve selection, a single bit of GC[3:0]
+    pub curve: u8,
+}
+
+impl GammaSet {
+    pub const fn validate(&self) -> Result<(), RangeError> {
+        if !matches!(self.curve, 0x01 | 0x02 | 0x04 | 0x08) {
+            return Err(RangeError::new(C::LCD_GAMMA, "curve"));
+        }
+        Ok(())
+    }
+
+    pub const fn encode(&self) -> [u8; 1] {
+        [self.curve]
+    }
+
+    pub const fn decode(p: [u8; 1]) -> Self {
+        Self { curve: p[0] }
+    }
+}
+
+/// Valid bits of each gamma correction parameter
+const GAMMA_MASKS: [u8; 15] = [
+    0x0f, 0x3f, 0x3f, 0x0f, 0x1f, 0x0f, 0x7f, 0xff, 0x7f, 0x0f, 0x1f, 0x0f, 0x3f, 0x3f, 0x0f,
+];
+
+/// Grayscale voltage levels set by each gamma correction parameter, the 8th packs two
+const GAMMA_FIELDS: [&str; 15] = [
+    "v63", "v62", "v61", "v59", "v57", "v50", "v43", "v36/v27", "v20", "v13", "v6", "v4", "v2",
+    "v1", "v0",
+];
+
+/// Positive (E0h) and Negative (E1h) Gamma Correction, the 15 parameters as sent
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GammaCorrection<const CMD: u8> {
+    pub params: [u8; 15],
+}
+
+pub type PositiveGamma = GammaCorrection<{ C::LCD_PGAMMA }>;
+pub type NegativeGamma = GammaCorrection<{ C::LCD_NGAMMA }>;
+
+impl<const CMD: u8> GammaCorrection<CMD> {
+    pub const fn validate(&self) -> Result<(), RangeError> {
+        let mut i = 0;
+        while i < GAMMA_MASKS.len() {
+            if self.params[i] & !GAMMA_MASKS[i] != 0 {
+                return Err(RangeError::new(CMD, GAMMA_FIELDS[i]));
+            }
+            i += 1;
+        }
+        Ok(())
+    }
+
+    pub const fn encode(&self) -> [u8; 15] {
+        self.params
+    }
+
+    pub const fn decode(p: [u8; 15]) -> Self {
+        Self { params: p }
+    }
+}
+
+/// Digital Gamma Control 1 (E2h, 16 entries) and 2 (E3h, 64 entries)
+///
+/// Each entry holds a red offset in its high nibble and a blue offset in its low nibble;
+/// the tables only take effect with [`Enable3Gamma`] set.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DigitalGamma<const CMD: u8, const N: usize> {
+    pub params: [u8; N],
+}
+
+pub type DigitalGamma1 = DigitalGamma<{ C::LCD_DGAMCTRL1 }, 16>;
+pub type DigitalGamma2 = DigitalGamma<{ C::LCD_DGAMCTRL2 }, 64>;
+
+impl<const CMD: u8, const N: usize> DigitalGamma<CMD, N> {
+    pub const fn validate(&self) -> Result<(), RangeError> {
+        Ok(())
+    }
+
+    pub const fn encode(&self) -> [u8; N] {
+        self.params
+    }
+
+    pub const fn decode(p: [u8; N]) -> Self {
+        Self { params: p }
+    }
+}
+
+/// Memory Access Control (36h), read back with RDDMADCTL (0Bh)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MemoryAccessControl {
+    /// Row address order
+    pub my: bool,
+    /// Column address order
+    pub mx: bool,
+    /// Row/column exchange
+    pub mv: bool,
+    /// Vertical refresh order
+    pub ml: bool,
+    /// BGR colour filter panel
+    pub bgr: bool,
+    /// Horizontal refresh order
+    pub mh: bool,
+}
+
+impl MemoryAccessControl {
+    pub const fn validate(&self) -> Result<(), RangeError> {
+        Ok(())
+    }
+
+    pub const fn encode(&self) -> [u8; 1] {
+        [
+            bit(self.my, 7) |
+                bit(self.mx, 6) |
+                bit(self.mv, 5) |
+                bit(self.ml, 4) |
+                bit(self.bgr, 3) |
+                bit(self.mh, 2),
+        ]
+    }
+
+    pub const fn decode(p: [u8; 1]) -> Self {
+        Self {
+            my: is_set(p[0], 7),
+            mx: is_set(p[0], 6),
+            mv: is_set(p[0], 5),
+            ml: is_set(p[0], 4),
+            bgr: is_set(p[0], 3),
+            mh: is_set(p[0], 2),
+        }
+    }
+}
+
+/// Pixel Format Set (3Ah), read back with RDDCOLMOD (0Ch)
+///
+/// 0b101 selects 16 bits/pixel and 0b110 18 bits/pixel for either interface.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PixelFormatSet {
+    /// RGB interface format
+    pub dpi: u8,
+    /// MCU interface format
+    pub dbi: u8,
+}
+
+impl PixelFormatSet {
+    pub const fn validate(&self) -> Result<(), RangeError> {
+        if self.dpi != 0b101 && self.dpi != 0b110 {
+            return Err(RangeError::new(C::LCD_PIXEL_FORMAT, "dpi"));
+        }
+        if self.dbi != 0b101 && self.dbi != 0b110 {
+            return Err(RangeError::new(C::LCD_PIXEL_FORMAT, "dbi"));
+        }
+        Ok(())
+    }
+
+    pub const fn encode(&self) -> [u8; 1] {
+        [((self.dpi & 0b111) << 4) | (self.dbi & 0b111)]
+    }
+
+    pub const fn decode(p: [u8; 1]) -> Self {
+        Self { dpi: (p[0] >> 4) & 0b111, dbi: p[0] & 0b111 }
+    }
+}
+
+/// Column Address Set (2Ah), start and end column inclusive
+///
+/// Columns run along the logical x axis, so the upper bound is 239 in portrait and 319
+/// with MADCTL MV set; only the wider limit is checked here.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ColumnAddress {
+    pub start: u16,
+    pub end: u16,
+}
+
+impl ColumnAddress {
+    pub const fn validate(&self) -> Result<(), RangeError> {
+        if self.start > self.end {
+            return Err(RangeError::new(C::LCD_COLUMN_ADDR, "start"));
+        }
+        if self.end as usize >= C::ILI9341_LCD_PIXEL_HEIGHT {
+            return Err(RangeError::new(C::LCD_COLUMN_ADDR, "end"));
+        }
+        Ok(())
+    }
+
+    pub const fn encode(&self) -> [u8; 4] {
+        let [s0, s1] = self.start.to_be_bytes();
+        let [e0, e1] = self.end.to_be_bytes();
+        [s0, s1, e0, e1]
+    }
+
+    pub const fn decode(p: [u8; 4]) -> Self {
+        Self {
+            start: u16::from_be_bytes([p[0], p[1]]),
+            end: u16::from_be_bytes([p[2], p[3]]),
+        }
+    }
+}
+
+/// Page Address Set (2Bh), start and end page inclusive
+///
+/// Pages run along the logical y axis, with the same orientation caveat as
+/// [`ColumnAddress`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PageAddress {
+    pub start: u16,
+    pub end: u16,
+}
+
+impl PageAddress {
+    pub const fn validate(&self) -> Result<(), RangeError> {
+        if self.start > self.end {
+            return Err(RangeError::new(C::LCD_PAGE_ADDR, "start"));
+        }
+        if self.end as usize >= C::ILI9341_LCD_PIXEL_HEIGHT {
+            return Err(RangeError::new(C::LCD_PAGE_ADDR, "end"));
+        }
+        Ok(())
+    }
+
+    pub const fn encode(&self) -> [u8; 4] {
+        let [s0, s1] = self.start.to_be_bytes();
+        let [e0, e1] = self.end.to_be_bytes();
+        [s0, s1, e0, e1]
+    }
+
+    pub const fn decode(p: [u8; 4]) -> Self {
+        Self {
+            start: u16::from_be_bytes([p[0], p[1]]),
+            end: u16::from_be_bytes([p[2], p[3]]),
+        }
+    }
+}
+
+/// Partial Area (30h), start and end row inclusive
+///
+/// An end row before the start row wraps the area around the bottom of the panel.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PartialArea {
+    pub start_row: u16,
+    pub end_row: u16,
+}
+
+impl PartialArea {
+    pub const fn validate(&self) -> Result<(), RangeError> {
+        if self.start_row as usize >= C::ILI9341_LCD_PIXEL_HEIGHT {
+            return Err(RangeError::new(C::LCD_PLTAR, "start_row"));
+        }
+        if self.end_row as usize >= C::ILI9341_LCD_PIXEL_HEIGHT {
+            return Err(RangeError::new(C::LCD_PLTAR, "end_row"));
+        }
+        Ok(())
+    }
+
+    pub const fn encode(&self) -> [u8; 4] {
+        let [s0, s1] = self.start_row.to_be_bytes();
+        let [e0, e1] = self.end_row.to_be_bytes();
+        [s0, s1, e0, e1]
+    }
+
+    pub const fn decode(p: [u8; 4]) -> Self {
+        Self {
+            start_row: u16::from_be_bytes([p[0], p[1]]),
+            end_row: u16::from_be_bytes([p[2], p[3]]),
+        }
+    }
+}
+
+/// Vertical Scrolling Definition (33h), in lines
+///
+/// The three areas have to cover the 320 lines of the panel exactly.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VerticalScrollDefinition {
+    pub top_fixed: u16,
+    pub scroll: u16,
+    pub bottom_fixed: u16,
+}
+
+impl VerticalScrollDefinition {
+    pub const fn validate(&self) -> Result<(), RangeError> {
+        let lines = C::ILI9341_LCD_PIXEL_HEIGHT as u32;
+        if (self.top_fixed as u32) + (self.scroll as u32) + (self.bottom_fixed as u32) != lines {
+            return Err(RangeError::new(C::LCD_VSCRDEF, "lines"));
+        }
+        Ok(())
+    }
+
+    pub const fn encode(&self) -> [u8; 6] {
+        let [t0, t1] = self.top_fixed.to_be_bytes();
+        let [s0, s1] = self.scroll.to_be_bytes();
+        let [b0, b1] = self.bottom_fixed.to_be_bytes();
+        [t0, t1, s0, s1, b0, b1]
+    }
+
+    pub const fn decode(p: [u8; 6]) -> Self {
+        Self {
+            top_fixed: u16::from_be_bytes([p[0], p[1]]),
+            scroll: u16::from_be_bytes([p[2], p[3]]),
+            bottom_fixed: u16::from_be_bytes([p[4], p[5]]),
+        }
+    }
+}
+
+/// Vertical Scrolling Start Address (37h), the GRAM line shown first in the scroll area
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VerticalScrollStart {
+    pub line: u16,
+}
+
+impl VerticalScrollStart {
+    pub const fn validate(&self) -> Result<(), RangeError> {
+        if self.line as usize >= C::ILI9341_LCD_PIXEL_HEIGHT {
+            return Err(RangeError::new(C::LCD_VSCRSADD, "line"));
+        }
+        Ok(())
+    }
+
+    pub const fn encode(&self) -> [u8; 2] {
+        self.line.to_be_bytes()
+    }
+
+    pub const fn decode(p: [u8; 2]) -> Self {
+        Self { line: u16::from_be_bytes(p) }
+    }
+}
+
+/// Tearing Effect Line ON (35h)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TearingEffectOn {
+    /// Pulse TE on H-blanking as well as on V-blanking
+    pub vh_blanking: bool,
+}
+
+impl TearingEffectOn {
+    pub const fn validate(&self) -> Result<(), RangeError> {
+        Ok(())
+    }
+
+    pub const fn encode(&self) -> [u8; 1] {
+        [bit(self.vh_blanking, 0)]
+    }
+
+    pub const fn decode(p: [u8; 1]) -> Self {
+        Self { vh_blanking: is_set(p[0], 0) }
+    }
+}
+
+/// Set Tear Scanline (44h), TE goes high when the panel reaches `line`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TearScanline {
+    pub line: u16,
+}
+
+impl TearScanline {
+    pub const fn validate(&self) -> Result<(), RangeError> {
+        if self.line as usize >= C::ILI9341_LCD_PIXEL_HEIGHT {
+            return Err(RangeError::new(C::LCD_SET_TEAR_SCANLINE, "line"));
+        }
+        Ok(())
+    }
+
+    pub const fn encode(&self) -> [u8; 2] {
+        [((self.line >> 8) & 0x01) as u8, self.line as u8]
+    }
+
+    pub const fn decode(p: [u8; 2]) -> Self {
+        Self { line: u16::from_be_bytes([p[0] & 0x01, p[1]]) }
+    }
+}
+
+/// Write CTRL Display (53h), read back with RDCTRLD (54h)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ControlDisplay {
+    /// Brightness control block on, brightness follows the DBV register
+    pub bctrl: bool,
+    /// Dimming on, brightness changes fade over the frames set in Backlight Control 5
+    pub dd: bool,
+    /// Backlight on
+    pub bl: bool,
+}
+
+impl ControlDisplay {
+    pub const fn validate(&self) -> Result<(), RangeError> {
+        Ok(())
+    }
+
+    pub const fn encode(&self) -> [u8; 1] {
+        [bit(self.bctrl, 5) | bit(self.dd, 3) | bit(self.bl, 2)]
+    }
+
+    pub const fn decode(p: [u8; 1]) -> Self {
+        Self { bctrl: is_set(p[0], 5), dd: is_set(p[0], 3), bl: is_set(p[0], 2) }
+    }
+}
+
+/// Content Adaptive Brightness Control mode (55h), read back with RDCABC (56h)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CabcMode {
+    Off,
+    UserInterface,
+    StillPicture,
+    MovingImage,
+}
+
+impl CabcMode {
+    pub const fn validate(&self) -> Result<(), RangeError> {
+        Ok(())
+    }
+
+    pub const fn encode(&self) -> [u8; 1] {
+        [
+            match self {
+                CabcMode::Off => 0b00,
+                CabcMode::UserInterface => 0b01,
+                CabcMode::StillPicture => 0b10,
+                CabcMode::MovingImage => 0b11,
+            },
+        ]
+    }
+
+    pub const fn decode(p: [u8; 1]) -> Self {
+        match p[0] & 0b11 {
+            0b00 => CabcMode::Off,
+            0b01 => CabcMode::UserInterface,
+            0b10 => CabcMode::StillPicture,
+            _ => CabcMode::MovingImage,
+        }
+    }
+}
+
+/// Backlight Control 1 (B8h), CABC grayscale histogram threshold in user interface mode
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BacklightThresholdUi {
+    pub th_ui: u8,
+}
+
+impl BacklightThresholdUi {
+    pub const fn validate(&self) -> Result<(), RangeError> {
+        if self.th_ui > 0x0f {
+            return Err(RangeError::new(C::LCD_BACKLIGHT1, "th_ui"));
+        }
+        Ok(())
+    }
+
+    pub const fn encode(&self) -> [u8; 1] {
+        [self.th_ui & 0x0f]
+    }
+
+    pub const fn decode(p: [u8; 1]) -> Self {
+        Self { th_ui: p[0] & 0x0f }
+    }
+}
+
+/// Backlight Control 2 (B9h), CABC histogram thresholds in moving image and still picture
+/// mode
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BacklightThreshold {
+    pub th_mv: u8,
+    pub th_st: u8,
+}
+
+impl BacklightThreshold {
+    pub const fn validate(&self) -> Result<(), RangeError> {
+        if self.th_mv > 0x0f {
+            return Err(RangeError::new(C::LCD_BACKLIGHT2, "th_mv"));
+        }
+        if self.th_st > 0x0f {
+            return Err(RangeError::new(C::LCD_BACKLIGHT2, "th_st"));
+        }
+        Ok(())
+    }
+
+    pub const fn encode(&self) -> [u8; 1] {
+        [((self.th_mv & 0x0f) << 4) | (self.th_st & 0x0f)]
+    }
+
+    pub const fn decode(p: [u8; 1]) -> Self {
+        Self { th_mv: p[0] >> 4, th_st: p[0] & 0x0f }
+    }
+}
+
+/// Backlight Control 3 (BAh), lowest CABC grayscale threshold in user interface mode
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BacklightMinimumUi {
+    pub dth_ui: u8,
+}
+
+impl BacklightMinimumUi {
+    pub const fn validate(&self) -> Result<(), RangeError> {
+        if self.dth_ui > 0x0f {
+            return Err(RangeError::new(C::LCD_BACKLIGHT3, "dth_ui"));
+        }
+        Ok(())
+    }
+
+    pub const fn encode(&self) -> [u8; 1] {
+        [self.dth_ui & 0x0f]
+    }
+
+    pub const fn decode(p: [u8; 1]) -> Self {
+        Self { dth_ui: p[0] & 0x0f }
+    }
+}
+
+/// Backlight Control 4 (BBh), lowest CABC grayscale thresholds in moving image and still
+/// picture mode
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BacklightMinimum {
+    pub dth_mv: u8,
+    pub dth_st: u8,
+}
+
+impl BacklightMinimum {
+    pub const fn validate(&self) -> Result<(), RangeError> {
+        if self.dth_mv > 0x0f {
+            return Err(RangeError::new(C::LCD_BACKLIGHT4, "dth_mv"));
+        }
+        if self.dth_st > 0x0f {
+            return Err(RangeError::new(C::LCD_BACKLIGHT4, "dth_st"));
+        }
+        Ok(())
+    }
+
+    pub const fn encode(&self) -> [u8; 1] {
+        [((self.dth_mv & 0x0f) << 4) | (self.dth_st & 0x0f)]
+    }
+
+    pub const fn decode(p: [u8; 1]) -> Self {
+        Self { dth_mv: p[0] >> 4, dth_st: p[0] & 0x0f }
+    }
+}
+
+/// Backlight Control 5 (BCh), dimming applied with CTRL Display DD set
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BacklightDimming {
+    /// Brightness change threshold
+    pub dim2: u8,
+    /// Transition time of a brightness change
+    pub dim1: u8,
+}
+
+impl BacklightDimming {
+    pub const fn validate(&self) -> Result<(), RangeError> {
+        if self.dim2 > 0b111 {
+            return Err(RangeError::new(C::LCD_BACKLIGHT5, "dim2"));
+        }
+        if self.dim1 > 0b111 {
+            return Err(RangeError::new(C::LCD_BACKLIGHT5, "dim1"));
+        }
+        Ok(())
+    }
+
+    pub const fn encode(&self) -> [u8; 1] {
+        [((self.dim2 & 0b111) << 4) | (self.dim1 & 0b111)]
+    }
+
+    pub const fn decode(p: [u8; 1]) -> Self {
+        Self { dim2: (p[0] >> 4) & 0b111, dim1: p[0] & 0b111 }
+    }
+}
+
+/// Backlight Control 7 (BEh), LEDPWM frequency divider
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BacklightPwm {
+    pub pwm_div: u8,
+}
+
+impl BacklightPwm {
+    pub const fn validate(&self) -> Result<(), RangeError> {
+        Ok(())
+    }
+
+    pub const fn encode(&self) -> [u8; 1] {
+        [self.pwm_div]
+    }
+
+    pub const fn decode(p: [u8; 1]) -> Self {
+        Self { pwm_div: p[0] }
+    }
+}
+
+/// Backlight Control 8 (BFh), LEDON and LEDPWM pin behaviour
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BacklightPins {
+    /// Level of LEDON when not driven by the brightness control block
+    pub ledonr: bool,
+    /// LEDON polarity
+    pub ledonpol: bool,
+    /// LEDPWM polarity
+    pub ledpwmpol: bool,
+}
+
+impl BacklightPins {
+    pub const fn validate(&self) -> Result<(), RangeError> {
+        Ok(())
+    }
+
+    pub const fn encode(&self) -> [u8; 1] {
+        [bit(self.ledonr, 2) | bit(self.ledonpol, 1) | bit(self.ledpwmpol, 0)]
+    }
+
+    pub const fn decode(p: [u8; 1]) -> Self {
+        Self { ledonr: is_set(p[0], 2), ledonpol: is_set(p[0], 1), ledpwmpol: is_set(p[0], 0) }
+    }
+}
+
+/// Display identification information (04h)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DisplayId {
+    pub manufacturer: u8,
+    pub version: u8,
+    pub driver: u8,
+}
+
+impl DisplayId {
+    pub const fn decode(p: [u8; 3]) -> Self {
+        Self { manufacturer: p[0], version: p[1], driver: p[2] }
+    }
+}
+
+/// Read ID4 (D3h): IC version and IC model, 0x9341 for this controller
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Id4 {
+    pub version: u8,
+    pub model: u16,
+}
+
+impl Id4 {
+    pub const ILI9341: u16 = 0x9341;
+
+    pub const fn decode(p: [u8; 3]) -> Self {
+        Self { version: p[0], model: u16::from_be_bytes([p[1], p[2]]) }
+    }
+}
+
+/// Display Power Mode (0Ah)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PowerMode {
+    pub booster_on: bool,
+    pub idle: bool,
+    pub partial: bool,
+    pub sleep_out: bool,
+    pub normal_mode: bool,
+    pub display_on: bool,
+}
+
+impl PowerMode {
+    pub const fn decode(p: [u8; 1]) -> Self {
+        Self {
+            booster_on: is_set(p[0], 7),
+            idle: is_set(p[0], 6),
+            partial: is_set(p[0], 5),
+            sleep_out: is_set(p[0], 4),
+            normal_mode: is_set(p[0], 3),
+            display_on: is_set(p[0], 2),
+        }
+    }
+}
+
+/// Display Status (09h)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DisplayStatus {
+    pub booster_on: bool,
+    pub madctl: MemoryAccessControl,
+    /// Interface pixel format, same encoding as [`PixelFormatSet`]
+    pub pixel_format: u8,
+    pub idle: bool,
+    pub partial: bool,
+    pub sleep_out: bool,
+    pub normal_mode: bool,
+    pub scrolling: bool,
+    pub inverted: bool,
+    pub display_on: bool,
+    pub tearing_on: bool,
+    /// Gamma curve selection, GC0..GC3
+    pub gamma_curve: u8,
+    /// Tearing effect mode, set for V-blank and H-blank
+    pub tearing_mode: bool,
+}
+
+impl DisplayStatus {
+    pub const fn decode(p: [u8; 4]) -> Self {
+        Self {
+            booster_on: is_set(p[0], 7),
+            madctl: MemoryAccessControl::decode([(p[0] << 1) & 0xfc]),
+            pixel_format: (p[1] >> 4) & 0b111,
+            idle: is_set(p[1], 3),
+            partial: is_set(p[1], 2),
+            sleep_out: is_set(p[1], 1),
+            normal_mode: is_set(p[1], 0),
+            scrolling: is_set(p[2], 7),
+            inverted: is_set(p[2], 5),
+            display_on: is_set(p[2], 2),
+            tearing_on: is_set(p[2], 1),
+            gamma_curve: ((p[2] & 0x01) << 2) | (p[3] >> 6),
+            tearing_mode: is_set(p[3], 5),
+        }
+    }
+}
+
+/// Implement [`Register`] for types whose inherent `validate`/`encode`/`decode` take and
+/// return `[u8; len]`
+macro_rules! impl_register {
+    (@items $cmd:expr, $len:expr) => {
+        const CMD: u8 = $cmd;
+        type Params = [u8; $len];
+
+        fn validate(&self) -> Result<(), RangeError> {
+            self.validate()
+        }
+
+        fn encode(&self) -> [u8; $len] {
+            self.encode()
+        }
+
+        fn decode(params: [u8; $len]) -> Self {
+            Self::decode(params)
+        }
+    };
+    (<$(const $param:ident: $param_ty:ty),+> $reg:ty, $cmd:expr, $len:expr) => {
+        impl<$(const $param: $param_ty),+> Register for $reg {
+            impl_register!(@items $cmd, $len);
+        }
+    };
+    ($($reg:ty, $cmd:expr, $len:expr;)+) => {
+        $(
+            impl Register for $reg {
+                impl_register!(@items $cmd, $len);
+            }
+        )+
+    };
+}
+
+impl_register! {
+    RgbInterfaceControl, C::LCD_RGB_INTERFACE, 1;
+    InversionControl, C::LCD_INVTR, 1;
+    BlankingPorchControl, C::LCD_BPC, 4;
+    DisplayFunctionControl, C::LCD_DFC, 4;
+    EntryMode, C::LCD_ETMOD, 1;
+    PowerControl1, C::LCD_POWER1, 1;
+    PowerControl2, C::LCD_POWER2, 1;
+    VcomControl, C::LCD_VCOM1, 2;
+    VcomOffset, C::LCD_VCOM2, 1;
+    InterfaceControl, C::LCD_INTERFACE, 3;
+    PowerControlA, C::LCD_POWERA, 5;
+    PowerControlB, C::LCD_POWERB, 3;
+    DriverTimingA, C::LCD_DTCA, 3;
+    DriverTimingB, C::LCD_DTCB, 2;
+    PowerOnSequence, C::LCD_POWER_SEQ, 4;
+    Enable3Gamma, C::LCD_3GAMMA_EN, 1;
+    GammaSet, C::LCD_GAMMA, 1;
+    MemoryAccessControl, C::LCD_MAC, 1;
+    PixelFormatSet, C::LCD_PIXEL_FORMAT, 1;
+    ColumnAddress, C::LCD_COLUMN_ADDR, 4;
+    PageAddress, C::LCD_PAGE_ADDR, 4;
+    PartialArea, C::LCD_PLTAR, 4;
+    VerticalScrollDefinition, C::LCD_VSCRDEF, 6;
+    VerticalScrollStart, C::LCD_VSCRSADD, 2;
+    TearingEffectOn, C::LCD_TEON, 1;
+    TearScanline, C::LCD_SET_TEAR_SCANLINE, 2;
+    ControlDisplay, C::LCD_WCD, 1;
+    CabcMode, C::LCD_WRCABC, 1;
+    BacklightThresholdUi, C::LCD_BACKLIGHT1, 1;
+    BacklightThreshold, C::LCD_BACKLIGHT2, 1;
+    BacklightMinimumUi, C::LCD_BACKLIGHT3, 1;
+    BacklightMinimum, C::LCD_BACKLIGHT4, 1;
+    BacklightDimming, C::LCD_BACKLIGHT5, 1;
+    BacklightPwm, C::LCD_BACKLIGHT7, 1;
+    BacklightPins, C::LCD_BACKLIGHT8, 1;
+    PumpRatioControl, C::LCD_PRC, 1;
+}
+
+impl_register!(<const CMD: u8> FrameRateControl<CMD>, CMD, 2);
+impl_register!(<const CMD: u8> GammaCorrection<CMD>, CMD, 15);
+impl_register!(<const CMD: u8, const N: usize> DigitalGamma<CMD, N>, CMD, N);
+
+const fn same(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    let mut i = 0;
+    while i < a.len() {
+        if a[i] != b[i] {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+// The gamma presets are plain tables, nothing else checks them.
+const _: () = {
+    let r = GammaCurve::ST_REFERENCE;
+    assert!(r.gamma_set.validate().is_ok());
+    assert!(r.positive.validate().is_ok() && r.negative.validate().is_ok());
+    let r = GammaCurve::ADAFRUIT;
+    assert!(r.gamma_set.validate().is_ok());
+    assert!(r.positive.validate().is_ok() && r.negative.validate().is_ok());
+};
+
+/// `result` is a range error on `field`
+const fn rejects(result: Result<(), RangeError>, field: &str) -> bool {
+    match result {
+        Err(err) => same(err.field.as_bytes(), field.as_bytes()),
+        Ok(()) => false,
+    }
+}
+
+// Every validated field must reject a value just outside its range.
+const _: () = {
+    let r = RgbInterfaceControl::decode([0xc2]);
+    assert!(rejects(RgbInterfaceControl { rcm: 0b100, ..r }.validate(), "rcm"));
+
+    let r = FrameRateNormal::decode([0x00, 0x1b]);
+    assert!(rejects(FrameRateNormal { div: 0b100, ..r }.validate(), "div"));
+    assert!(rejects(FrameRateNormal { rtn: 0x0f, ..r }.validate(), "rtn"));
+    assert!(rejects(FrameRateNormal { rtn: 0x20, ..r }.validate(), "rtn"));
+
+    let r = BlankingPorchControl { vfp: 2, vbp: 2, hfp: 10, hbp: 20 };
+    assert!(r.validate().is_ok());
+    assert!(rejects(BlankingPorchControl { vfp: 1, ..r }.validate(), "vfp"));
+    assert!(rejects(BlankingPorchControl { vfp: 0x80, ..r }.validate(), "vfp"));
+    assert!(rejects(BlankingPorchControl { vbp: 0x80, ..r }.validate(), "vbp"));
+    assert!(rejects(BlankingPorchControl { hfp: 0x20, ..r }.validate(), "hfp"));
+    assert!(rejects(BlankingPorchControl { hbp: 1, ..r }.validate(), "hbp"));
+    assert!(rejects(BlankingPorchControl { hbp: 0x20, ..r }.validate(), "hbp"));
+
+    let r = DisplayFunctionControl::decode([0x0a, 0xa7, 0x27, 0x04]);
+    assert!(rejects(DisplayFunctionControl { ptg: 0b100, ..r }.validate(), "ptg"));
+    assert!(rejects(DisplayFunctionControl { pt: 0b100, ..r }.validate(), "pt"));
+    assert!(rejects(DisplayFunctionControl { isc: 0x10, ..r }.validate(), "isc"));
+    assert!(rejects(DisplayFunctionControl { nl: 0x28, ..r }.validate(), "nl"));
+    assert!(rejects(DisplayFunctionControl { pcdiv: 0x40, ..r }.validate(), "pcdiv"));
+
+    assert!(rejects(PowerControl1 { vrh: 0x02 }.validate(), "vrh"));
+    assert!(rejects(PowerControl1 { vrh: 0x40 }.validate(), "vrh"));
+
+    let r = PowerControl2::decode([0x10]);
+    assert!(rejects(PowerControl2 { sap: 0b1000, ..r }.validate(), "sap"));
+    assert!(rejects(PowerControl2 { bt: 0b100, ..r }.validate(), "bt"));
+
+    let r = VcomControl::decode([0x45, 0x15]);
+    assert!(rejects(VcomControl { vmh: 0x65, ..r }.validate(), "vmh"));
+    assert!(rejects(VcomControl { vml: 0x65, ..r }.validate(), "vml"));
+
+    assert!(rejects(VcomOffset { nvm: true, vmf: 0x80 }.validate(), "vmf"));
+
+    let r = InterfaceControl::decode([0x01, 0x00, 0x06]);
+    assert!(rejects(InterfaceControl { epf: 0b100, ..r }.validate(), "epf"));
+    assert!(rejects(InterfaceControl { mdt: 0b100, ..r }.validate(), "mdt"));
+    assert!(rejects(InterfaceControl { dm: 0b11, ..r }.validate(), "dm"));
+
+    let r = PowerControlA::decode([0x39, 0x2c, 0x00, 0x34, 0x02]);
+    assert!(rejects(PowerControlA { reg_vd: 0b110, ..r }.validate(), "reg_vd"));
+    assert!(rejects(PowerControlA { vbc: 0b111, ..r }.validate(), "vbc"));
+
+    let r = PowerControlB::decode([0x00, 0xc1, 0x30]);
+    assert!(rejects(PowerControlB { power_control: 0b100, ..r }.validate(), "power_control"));
+
+    let r = DriverTimingA::decode([0x85, 0x00, 0x78]);
+    assert!(rejects(DriverTimingA { pc: 0b11, ..r }.validate(), "pc"));
+
+    let r = DriverTimingB::decode([0x00, 0x00]);
+    assert!(rejects(DriverTimingB { t1: 0b100, ..r }.validate(), "vg_sw_t"));
+    assert!(rejects(DriverTimingB { t4: 0b100, ..r }.validate(), "vg_sw_t"));
+
+    let r = PowerOnSequence::decode([0x64, 0x03, 0x12, 0x81]);
+    assert!(rejects(PowerOnSequence { cp1_soft_start: 0b100, ..r }.validate(), "soft_start"));
+    assert!(rejects(PowerOnSequence { en_vgl: 0b100, ..r }.validate(), "power_on_frame"));
+
+    assert!(rejects(PumpRatioControl { ratio: 1 }.validate(), "ratio"));
+    assert!(rejects(PumpRatioControl { ratio: 4 }.validate(), "ratio"));
+
+    assert!(rejects(GammaSet { curve: 0x03 }.validate(), "curve"));
+
+    assert!(rejects(BacklightThresholdUi { th_ui: 0x10 }.validate(), "th_ui"));
+    assert!(rejects(BacklightThreshold { th_mv: 0x10, th_st: 0 }.validate(), "th_mv"));
+    assert!(rejects(BacklightThreshold { th_mv: 0, th_st: 0x10 }.validate(), "th_st"));
+    assert!(rejects(BacklightMinimumUi { dth_ui: 0x10 }.validate(), "dth_ui"));
+    assert!(rejects(BacklightMinimum { dth_mv: 0x10, dth_st: 0 }.validate(), "dth_mv"));
+    assert!(rejects(BacklightMinimum { dth_mv: 0, dth_st: 0x10 }.validate(), "dth_st"));
+    assert!(rejects(BacklightDimming { dim2: 0b1000, dim1: 0 }.validate(), "dim2"));
+    assert!(rejects(BacklightDimming { dim2: 0, dim1: 0b1000 }.validate(), "dim1"));
+
+    let mut r = GammaCurve::ST_REFERENCE.positive;
+    r.params[0] = 0x10;
+    assert!(rejects(r.validate(), "v63"));
+    let mut r = GammaCurve::ST_REFERENCE.negative;
+    r.params[14] = 0x10;
+    assert!(rejects(r.validate(), "v0"));
+
+    assert!(rejects(PixelFormatSet { dpi: 0b111, dbi: 0b110 }.validate(), "dpi"));
+    assert!(rejects(PixelFormatSet { dpi: 0b110, dbi: 0b100 }.validate(), "dbi"));
+
+    assert!(rejects(ColumnAddress { start: 10, end: 9 }.validate(), "start"));
+    assert!(rejects(ColumnAddress { start: 0, end: 320 }.validate(), "end"));
+    assert!(rejects(PageAddress { start: 10, end: 9 }.validate(), "start"));
+    assert!(rejects(PageAddress { start: 0, end: 320 }.validate(), "end"));
+    assert!(rejects(PartialArea { start_row: 320, end_row: 0 }.validate(), "start_row"));
+    assert!(rejects(PartialArea { start_row: 0, end_row: 320 }.validate(), "end_row"));
+
+    let r = VerticalScrollDefinition { top_fixed: 20, scroll: 281, bottom_fixed: 20 };
+    assert!(rejects(r.validate(), "lines"));
+    assert!(rejects(VerticalScrollStart { line: 320 }.validate(), "line"));
+    assert!(rejects(TearScanline { line: 320 }.validate(), "line"));
+};
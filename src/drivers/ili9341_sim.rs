@@ -0,0 +1,783 @@
+use core::{ convert::Infallible, mem };
+
+use embedded_hal::delay::DelayNs;
+
+use super::ili9341::{ Interface, ILI9341 };
+
+const WIDTH: usize = ILI9341::<(), ()>::ILI9341_LCD_PIXEL_WIDTH;
+const HEIGHT: usize = ILI9341::<(), ()>::ILI9341_LCD_PIXEL_HEIGHT;
+
+/// Parameters kept per command in the raw register file
+pub const MAX_PARAMS: usize = 16;
+
+/// Host-side model of the ILI9341 controller
+///
+/// Consumes the `write_reg`/`write_data` byte stream an [`ILI9341`] driver emits and
+/// decodes it into the state the real panel would end up in, so host tests can assert
+/// on MADCTL, pixel format, address window, power state, gamma tables and GRAM contents
+/// instead of on raw bytes.
+pub struct ILI9341Sim {
+    /// Memory Access Control (0x36)
+    pub madctl: u8,
+    /// Pixel Format Set (0x3a)
+    pub pixel_format: u8,
+    /// GRAM fed by the RGB interface (F6h RM), memory writes over SPI are ignored
+    pub rgb_interface: bool,
+    /// Column address window (0x2a), start and end inclusive
+    pub columns: (u16, u16),
+    /// Page address window (0x2b), start and end inclusive
+    pub pages: (u16, u16),
+    pub sleeping: bool,
+    pub display_on: bool,
+    /// Normal display mode (0x13) vs partial mode (0x12)
+    pub normal_mode: bool,
+    pub idle: bool,
+    /// Partial area rows (30h), start and end inclusive
+    pub partial_area: (u16, u16),
+    pub inverted: bool,
+    /// Vertical scrolling mode, entered with 33h and left with 12h/13h
+    pub scrolling: bool,
+    /// Top fixed, scroll and bottom fixed areas (33h)
+    pub scroll_area: (u16, u16, u16),
+    /// Vertical scrolling start address (37h)
+    pub scroll_start: u16,
+    /// TE output (35h/34h), `Some(true)` when pulsing on V- and H-blanking
+    pub tearing: Option<bool>,
+    /// Tear scanline (44h)
+    pub tear_scanline: u16,
+    /// Display brightness (51h)
+    pub brightness: u8,
+    /// CTRL Display (53h)
+    pub control_display: u8,
+    /// CABC mode (55h)
+    pub cabc_mode: u8,
+    /// CABC minimum brightness (5Eh)
+    pub cabc_min_brightness: u8,
+    /// Gamma curve selected with 0x26
+    pub gamma_curve: u8,
+    pub positive_gamma: [u8; 15],
+    pub negative_gamma: [u8; 15],
+    /// Digital gamma tables enabled with Enable 3G (F2h)
+    pub digital_gamma: bool,
+    /// ID1..ID3 returned by Read Display Identification (04h)
+    pub id: [u8; 3],
+    /// Graphic RAM, one RGB565 value per pixel in panel (unrotated) order
+    pub gram: Vec<u16>,
+
+    regs: [[u8; MAX_PARAMS]; 256],
+    reg_len: [usize; 256],
+    command: Option<u8>,
+    params: usize,
+    cursor: (u16, u16),
+    pixel: [u8; 3],
+    pixel_len: usize,
+}
+
+impl ILI9341Sim {
+    pub fn new() -> Self {
+        Self {
+            madctl: 0x00,
+            pixel_format: 0x66,
+            rgb_interface: false,
+            columns: (0, (WIDTH - 1) as u16),
+            pages: (0, (HEIGHT - 1) as u16),
+            sleeping: true,
+            display_on: false,
+            normal_mode: true,
+            idle: false,
+            partial_area: (0, (HEIGHT - 1) as u16),
+            inverted: false,
+            scrolling: false,
+            scroll_area: (0, HEIGHT as u16, 0),
+            scroll_start: 0,
+            tearing: None,
+            tear_scanline: 0,
+            brightness: 0x00,
+            control_display: 0x00,
+            cabc_mode: 0x00,
+            cabc_min_brightness: 0x00,
+            gamma_curve: 0x01,
+            positive_gamma: [0; 15],
+            negative_gamma: [0; 15],
+            digital_gamma: false,
+            id: [0; 3],
+            gram: vec![0; WIDTH * HEIGHT],
+            regs: [[0; MAX_PARAMS]; 256],
+            reg_len: [0; 256],
+            command: None,
+            params: 0,
+            cursor: (0, 0),
+            pixel: [0; 3],
+            pixel_len: 0,
+        }
+    }
+
+    /// Last parameters written to `reg`, truncated to [`MAX_PARAMS`] bytes
+    pub fn reg(&self, reg: u8) -> &[u8] {
+        &self.regs[reg as usize][..self.reg_len[reg as usize]]
+    }
+
+    /// GRAM pixel at panel coordinates `(x, y)`
+    pub fn pixel(&self, x: usize, y: usize) -> u16 {
+        self.gram[y * WIDTH + x]
+    }
+
+    fn reset(&mut self) {
+        let gram = mem::take(&mut self.gram);
+        let id = self.id;
+        *self = Self::new();
+        self.gram = gram;
+        self.id = id;
+    }
+
+    fn power_mode(&self) -> u8 {
+        ((!self.sleeping as u8) << 7) |
+            ((self.idle as u8) << 6) |
+            ((!self.normal_mode as u8) << 5) |
+            ((!self.sleeping as u8) << 4) |
+            ((self.normal_mode as u8) << 3) |
+            ((self.display_on as u8) << 2)
+    }
+
+    fn status(&self) -> [u8; 4] {
+        let gamma = self.gamma_curve.trailing_zeros() as u8 & 0b111;
+        [
+            ((!self.sleeping as u8) << 7) | ((self.madctl >> 1) & 0x7e),
+            ((self.pixel_format & 0b111) << 4) |
+                ((self.idle as u8) << 3) |
+                ((!self.normal_mode as u8) << 2) |
+                ((!self.sleeping as u8) << 1) |
+                (self.normal_mode as u8),
+            ((self.scrolling as u8) << 7) |
+                ((self.inverted as u8) << 5) |
+                ((self.display_on as u8) << 2) |
+                ((self.tearing.is_some() as u8) << 1) |
+                (gamma >> 2),
+            ((gamma & 0b11) << 6) | ((self.tearing == Some(true)) as u8) << 5,
+        ]
+    }
+
+    fn execute(&mut self, reg: u8) {
+        type C = ILI9341<(), ()>;
+
+        match reg {
+            C::LCD_SWRESET => self.reset(),
+            C::LCD_SPLIN => {
+                self.sleeping = true;
+            }
+            C::LCD_SLEEP_OUT => {
+                self.sleeping = false;
+            }
+            C::LCD_PTLON => {
+                self.normal_mode = false;
+                self.scrolling = false;
+            }
+            C::LCD_NORMAL_MODE_ON => {
+                self.normal_mode = true;
+                self.scrolling = false;
+            }
+            C::LCD_DINVOFF => {
+                self.inverted = false;
+            }
+            C::LCD_DINVON => {
+                self.inverted = true;
+            }
+            C::LCD_IDMOFF => {
+                self.idle = false;
+            }
+            C::LCD_IDMON => {
+                self.idle = true;
+            }
+            C::LCD_TEOFF => {
+                self.tearing = None;
+            }
+            C::LCD_DISPLAY_OFF => {
+                self.display_on = false;
+            }
+            C::LCD_DISPLAY_ON => {
+                self.display_on = true;
+            }
+            C::LCD_GRAM => {
+                self.cursor = (self.columns.0, self.pages.0);
+                self.pixel_len = 0;
+            }
+            C::LCD_WRITE_MEM_CONTINUE => {
+                self.pixel_len = 0;
+            }
+            _ => {}
+        }
+    }
+
+    fn parameter(&mut self, reg: u8, index: usize, data: u8) {
+        type C = ILI9341<(), ()>;
+
+        if index < MAX_PARAMS {
+            self.regs[reg as usize][index] = data;
+            self.reg_len[reg as usize] = index + 1;
+        }
+
+        let p = self.regs[reg as usize];
+        match (reg, index) {
+            (C::LCD_MAC, 0) => {
+                self.madctl = data;
+            }
+            (C::LCD_PIXEL_FORMAT, 0) => {
+                self.pixel_format = data;
+            }
+            (C::LCD_INTERFACE, 2) => {
+                self.rgb_interface = data & 0x02 != 0;
+            }
+            (C::LCD_GAMMA, 0) => {
+                self.gamma_curve = data;
+            }
+            (C::LCD_COLUMN_ADDR, 3) => {
+                self.columns = (u16::from_be_bytes([p[0], p[1]]), u16::from_be_bytes([p[2], p[3]]));
+            }
+            (C::LCD_PAGE_ADDR, 3) => {
+                self.pages = (u16::from_be_bytes([p[0], p[1]]), u16::from_be_bytes([p[2], p[3]]));
+            }
+            (C::LCD_PLTAR, 3) => {
+                self.partial_area = (
+                    u16::from_be_bytes([p[0], p[1]]),
+                    u16::from_be_bytes([p[2], p[3]]),
+                );
+            }
+            (C::LCD_TEON, 0) => {
+                self.tearing = Some(data & 0x01 != 0);
+            }
+            (C::LCD_SET_TEAR_SCANLINE, 1) => {
+                self.tear_scanline = u16::from_be_bytes([p[0] & 0x01, p[1]]);
+            }
+            (C::LCD_WDB, 0) => {
+                self.brightness = data;
+            }
+            (C::LCD_WCD, 0) => {
+                self.control_display = data & 0x2c;
+            }
+            (C::LCD_WRCABC, 0) => {
+                self.cabc_mode = data & 0b11;
+            }
+            (C::LCD_WRITE_CABC, 0) => {
+                self.cabc_min_brightness = data;
+            }
+            (C::LCD_VSCRDEF, 5) => {
+                self.scroll_area = (
+                    u16::from_be_bytes([p[0], p[1]]),
+                    u16::from_be_bytes([p[2], p[3]]),
+                    u16::from_be_bytes([p[4], p[5]]),
+                );
+                self.scrolling = true;
+            }
+            (C::LCD_VSCRSADD, 1) => {
+                self.scroll_start = u16::from_be_bytes([p[0], p[1]]);
+            }
+            (C::LCD_PGAMMA, 0..=14) => {
+                self.positive_gamma[index] = data;
+            }
+            (C::LCD_NGAMMA, 0..=14) => {
+                self.negative_gamma[index] = data;
+            }
+            (C::LCD_3GAMMA_EN, 0) => {
+                self.digital_gamma = data & 0x01 != 0;
+            }
+            (C::LCD_GRAM | C::LCD_WRITE_MEM_CONTINUE, _) if !self.rgb_interface => {
+                self.memory_write(data)
+            }
+            _ => {}
+        }
+    }
+
+    fn memory_write(&mut self, data: u8) {
+        self.pixel[self.pixel_len] = data;
+        self.pixel_len += 1;
+
+        // DBI[2:0] = 101 selects 16 bits/pixel, anything else is treated as 18 bits/pixel
+        let color = if self.pixel_format & 0x07 == 0x05 {
+            if self.pixel_len < 2 {
+                return;
+            }
+            u16::from_be_bytes([self.pixel[0], self.pixel[1]])
+        } else {
+            if self.pixel_len < 3 {
+                return;
+            }
+            let [r, g, b] = self.pixel;
+            (((r >> 3) as u16) << 11) | (((g >> 2) as u16) << 5) | ((b >> 3) as u16)
+        };
+        self.pixel_len = 0;
+
+        if let Some(index) = self.gram_index() {
+            self.gram[index] = color;
+        }
+        self.advance_cursor();
+    }
+
+    /// Serialise pixels from the cursor on as RAMRD clocks them out: one dummy byte,
+    /// then 18 bits/pixel whatever the pixel format
+    fn memory_read(&mut self, buf: &mut [u8]) {
+        let Some((dummy, data)) = buf.split_first_mut() else {
+            return;
+        };
+        *dummy = 0;
+
+        for rgb in data.chunks_mut(3) {
+            let color = self.gram_index().map_or(0, |index| self.gram[index]);
+            let wire = [
+                ((color >> 8) as u8) & 0xf8,
+                ((color >> 3) as u8) & 0xfc,
+                (color << 3) as u8,
+            ];
+            let len = rgb.len();
+            rgb.copy_from_slice(&wire[..len]);
+            self.advance_cursor();
+        }
+    }
+
+    fn advance_cursor(&mut self) {
+        let (col, page) = self.cursor;
+        self.cursor = if col < self.columns.1 {
+            (col + 1, page)
+        } else if page < self.pages.1 {
+            (self.columns.0, page + 1)
+        } else {
+            (self.columns.0, self.pages.0)
+        };
+    }
+
+    /// GRAM index the cursor points at after MADCTL mapping, `None` outside the panel
+    fn gram_index(&self) -> Option<usize> {
+        let (col, page) = self.cursor;
+        let (mut x, mut y) = if self.madctl & 0x20 != 0 {
+            (page as usize, col as usize)
+        } else {
+            (col as usize, page as usize)
+        };
+        if x >= WIDTH || y >= HEIGHT {
+            return None;
+        }
+        if self.madctl & 0x40 != 0 {
+            x = WIDTH - 1 - x;
+        }
+        if self.madctl & 0x80 != 0 {
+            y = HEIGHT - 1 - y;
+        }
+        Some(y * WIDTH + x)
+    }
+}
+
+impl Default for ILI9341Sim {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Delay that returns at once, the model has no notion of time
+pub struct NoDelay;
+
+impl DelayNs for NoDelay {
+    fn delay_ns(&mut self, _ns: u32) {}
+}
+
+impl Interface for ILI9341Sim {
+    type Error = Infallible;
+
+    fn write_reg(&mut self, reg: u8) -> Result<(), Infallible> {
+        self.command = Some(reg);
+        self.params = 0;
+        self.execute(reg);
+
+        Ok(())
+    }
+
+    fn write_data(&mut self, data: u8) -> Result<(), Infallible> {
+        if let Some(reg) = self.command {
+            self.parameter(reg, self.params, data);
+            self.params += 1;
+        }
+
+        Ok(())
+    }
+
+    fn read_data(&mut self, reg: u8, buf: &mut [u8]) -> Result<(), Infallible> {
+        type C = ILI9341<(), ()>;
+
+        self.command = None;
+
+        match reg {
+            C::LCD_RAMRD => {
+                self.cursor = (self.columns.0, self.pages.0);
+                self.memory_read(buf);
+                return Ok(());
+            }
+            C::LCD_READ_MEM_CONTINUE => {
+                self.memory_read(buf);
+                return Ok(());
+            }
+            _ => {}
+        }
+
+        // Serialise the response the way the 4-wire interface clocks it out: 24/32 bit
+        // reads start with one dummy clock, ID4 with one dummy parameter
+        let mut wire = [0u8; 5];
+        match reg {
+            C::LCD_READ_DISPLAY_ID => shift_in_dummy_bit(&self.id, &mut wire),
+            C::LCD_RDDST => shift_in_dummy_bit(&self.status(), &mut wire),
+            C::LCD_READ_ID4 => {
+                wire[1..4].copy_from_slice(&[0x00, 0x93, 0x41]);
+            }
+            C::LCD_RDDPM => {
+                wire[0] = self.power_mode();
+            }
+            C::LCD_RDDMADCTL => {
+                wire[0] = self.madctl;
+            }
+            C::LCD_RDDCOLMOD => {
+                wire[0] = self.pixel_format;
+            }
+            C::LCD_RDDISBV => {
+                wire[0] = self.brightness;
+            }
+            C::LCD_RDCTRLD => {
+                wire[0] = self.control_display;
+            }
+            C::LCD_RDCABC => {
+                wire[0] = self.cabc_mode;
+            }
+            C::LCD_READ_CABC => {
+                wire[0] = self.cabc_min_brightness;
+            }
+            _ => {}
+        }
+
+        let len = buf.len().min(wire.len());
+        buf[..len].copy_from_slice(&wire[..len]);
+
+        Ok(())
+    }
+}
+
+fn shift_in_dummy_bit(data: &[u8], wire: &mut [u8]) {
+    let mut carry = 0;
+    for (i, &byte) in data.iter().enumerate() {
+        wire[i] = carry | (byte >> 1);
+        carry = byte << 7;
+    }
+    wire[data.len()] = carry;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::drivers::{
+        ili9341::{
+            GammaCurve,
+            InterfaceMode,
+            Orientation,
+            PowerState,
+            Rgb565,
+            TearSignal,
+            TearingMode,
+            Verify,
+            Window,
+        },
+        ili9341_regs::{
+            BlankingPorchControl,
+            CabcMode,
+            DigitalGamma1,
+            DigitalGamma2,
+            FrameRateIdle,
+            FrameRatePartial,
+            PixelFormatSet,
+        },
+    };
+
+    type Driver = ILI9341<ILI9341Sim, NoDelay>;
+
+    fn init() -> Driver {
+        Driver::init(ILI9341Sim::new(), NoDelay, Verify::Readback).unwrap()
+    }
+
+    /// Pixels of the whole panel set to `color`
+    fn count(sim: &ILI9341Sim, color: u16) -> usize {
+        sim.gram.iter().filter(|&&pixel| pixel == color).count()
+    }
+
+    #[test]
+    fn init_state() {
+        let (sim, _) = init().release();
+
+        assert_eq!(sim.madctl, 0xc8);
+        assert_eq!(sim.pixel_format, 0x66);
+        assert!(sim.display_on);
+        assert!(!sim.sleeping);
+        assert!(sim.normal_mode && !sim.idle);
+        assert!(sim.rgb_interface);
+        assert_eq!(sim.columns, (0, 239));
+        assert_eq!(sim.pages, (0, 319));
+        assert_eq!(sim.reg(Driver::LCD_DFC), &[0x0a, 0xa7, 0x27, 0x04]);
+        assert_eq!(sim.gamma_curve, GammaCurve::ST_REFERENCE.gamma_set.curve);
+        assert_eq!(sim.positive_gamma, GammaCurve::ST_REFERENCE.positive.params);
+        assert_eq!(sim.negative_gamma, GammaCurve::ST_REFERENCE.negative.params);
+        assert!(!sim.digital_gamma);
+    }
+
+    #[test]
+    fn on_off() {
+        let mut ili9341 = init();
+
+        ili9341.off().unwrap();
+        let pm = ili9341.read_power_mode().unwrap();
+        assert!(!pm.display_on && pm.sleep_out);
+        ili9341.on().unwrap();
+        assert!(ili9341.read_power_mode().unwrap().display_on);
+        ili9341.off().unwrap();
+
+        let (sim, _) = ili9341.release();
+        assert!(!sim.display_on);
+        assert!(!sim.sleeping);
+    }
+
+    #[test]
+    fn readback() {
+        let mut sim = ILI9341Sim::new();
+        sim.id = [0xff, 0x81, 0x01];
+        let mut ili9341 = Driver::init(sim, NoDelay, Verify::Readback).unwrap();
+
+        let id = ili9341.read_id().unwrap();
+        assert_eq!((id.manufacturer, id.version, id.driver), (0xff, 0x81, 0x01));
+        assert_eq!(ili9341.read_id4().unwrap().model, 0x9341);
+
+        let status = ili9341.read_status().unwrap();
+        assert!(status.display_on && status.sleep_out);
+        assert!(status.madctl.my && status.madctl.mx && status.madctl.bgr && !status.madctl.mv);
+        assert_eq!(status.pixel_format, 0b110);
+
+        let pm = ili9341.read_power_mode().unwrap();
+        assert!(pm.display_on && pm.sleep_out && pm.normal_mode);
+        assert_eq!(ili9341.read_madctl().unwrap().encode(), [0xc8]);
+        assert_eq!(ili9341.read_pixel_format().unwrap().encode(), [0x66]);
+    }
+
+    #[test]
+    fn orientation() {
+        let mut ili9341 = init();
+        assert_eq!(ili9341.orientation(), Orientation::Portrait);
+        assert_eq!((ili9341.width(), ili9341.height()), (240, 320));
+
+        ili9341.set_orientation(Orientation::Landscape).unwrap();
+        assert_eq!((ili9341.width(), ili9341.height()), (320, 240));
+        assert_eq!(ili9341.read_madctl().unwrap().encode(), [0xa8]);
+
+        ili9341.set_mirror(true, false).unwrap();
+        assert_eq!(ili9341.read_madctl().unwrap().encode(), [0xe8]);
+    }
+
+    #[test]
+    fn scrolling() {
+        let mut ili9341 = init();
+        assert!(ili9341.define_scroll_area(10, 300, 20).is_err());
+        ili9341.define_scroll_area(20, 280, 20).unwrap();
+        assert!(ili9341.set_scroll_start(5).is_err());
+        ili9341.set_scroll_start(100).unwrap();
+        assert!(ili9341.read_status().unwrap().scrolling);
+
+        let (sim, _) = ili9341.release();
+        assert_eq!(sim.scroll_area, (20, 280, 20));
+        assert_eq!(sim.scroll_start, 100);
+    }
+
+    #[test]
+    fn partial_idle() {
+        let mut ili9341 = init();
+        let idle = FrameRateIdle { div: 3, rtn: 0x1f };
+
+        ili9341.enter_partial_mode(0, 19, &FrameRatePartial { div: 3, rtn: 0x1f }).unwrap();
+        let pm = ili9341.read_power_mode().unwrap();
+        assert!(pm.partial && !pm.normal_mode);
+
+        ili9341.enter_idle_mode(&idle).unwrap();
+        assert_eq!(ili9341.power_state(), PowerState::PartialIdle);
+        let pm = ili9341.read_power_mode().unwrap();
+        assert!(pm.partial && pm.idle);
+
+        // Each mode is left on its own
+        ili9341.normal_mode().unwrap();
+        assert_eq!(ili9341.power_state(), PowerState::Idle);
+        let pm = ili9341.read_power_mode().unwrap();
+        assert!(!pm.partial && pm.idle && pm.normal_mode);
+        assert!(ili9341.normal_mode().is_ok());
+
+        ili9341.enter_partial_mode(0, 19, &FrameRatePartial { div: 3, rtn: 0x1f }).unwrap();
+        ili9341.exit_idle_mode().unwrap();
+        assert_eq!(ili9341.power_state(), PowerState::Partial);
+        let pm = ili9341.read_power_mode().unwrap();
+        assert!(pm.partial && !pm.idle);
+        assert!(ili9341.exit_idle_mode().is_err());
+
+        ili9341.normal_mode().unwrap();
+        assert_eq!(ili9341.power_state(), PowerState::On);
+        let pm = ili9341.read_power_mode().unwrap();
+        assert!(!pm.partial && !pm.idle && pm.normal_mode);
+
+        let (sim, _) = ili9341.release();
+        assert_eq!(sim.partial_area, (0, 19));
+        assert_eq!(sim.reg(Driver::LCD_FRMCTR3), &[3, 0x1f]);
+    }
+
+    #[test]
+    fn power() {
+        let mut ili9341 = init();
+        assert_eq!(ili9341.power_state(), PowerState::On);
+        assert!(ili9341.wake().is_err());
+
+        ili9341.enter_idle_mode(&FrameRateIdle { div: 0, rtn: 0x1b }).unwrap();
+        ili9341.off().unwrap();
+        assert_eq!(ili9341.power_state(), PowerState::Off);
+        ili9341.on().unwrap();
+        assert_eq!(ili9341.power_state(), PowerState::Idle);
+        ili9341.sleep().unwrap();
+        assert!(ili9341.on().is_err());
+        let pm = ili9341.read_power_mode().unwrap();
+        assert!(pm.idle && !pm.sleep_out);
+        assert!(ili9341.sleep().is_err());
+
+        ili9341.wake().unwrap();
+        assert_eq!(ili9341.power_state(), PowerState::Idle);
+        ili9341.exit_idle_mode().unwrap();
+        ili9341.off().unwrap();
+        assert!(!ili9341.read_power_mode().unwrap().display_on);
+    }
+
+    #[test]
+    fn on_off_keeps_partial_mode() {
+        let mut ili9341 = init();
+        ili9341.enter_partial_mode(0, 19, &FrameRatePartial { div: 0, rtn: 0x1b }).unwrap();
+
+        ili9341.off().unwrap();
+        assert_eq!(ili9341.power_state(), PowerState::Off);
+        let pm = ili9341.read_power_mode().unwrap();
+        assert!(!pm.display_on && pm.partial);
+
+        ili9341.on().unwrap();
+        assert_eq!(ili9341.power_state(), PowerState::Partial);
+        assert!(ili9341.read_power_mode().unwrap().display_on);
+    }
+
+    #[test]
+    fn tearing() {
+        /// Counts the waits instead of blocking on a pin
+        struct Te(u32);
+
+        impl TearSignal for Te {
+            type Error = Infallible;
+
+            fn wait_for_tear(&mut self) -> Result<(), Infallible> {
+                self.0 += 1;
+                Ok(())
+            }
+        }
+
+        let mut ili9341 = init();
+        let mut te = Te(0);
+        assert!(ili9341.synchronized(&mut te, |_| Ok(())).is_err());
+
+        ili9341.enable_tearing_effect(TearingMode::VHBlank).unwrap();
+        ili9341.set_tear_scanline(300).unwrap();
+        assert!(ili9341.set_tear_scanline(320).is_err());
+        ili9341.synchronized(&mut te, |ili9341| ili9341.on()).unwrap();
+        assert_eq!(te.0, 1);
+
+        let status = ili9341.read_status().unwrap();
+        assert!(status.tearing_on && status.tearing_mode);
+        let (sim, _) = ili9341.release();
+        assert_eq!(sim.tear_scanline, 300);
+    }
+
+    #[test]
+    fn brightness() {
+        let mut ili9341 = init();
+        ili9341.enable_brightness_control(true).unwrap();
+        ili9341.set_brightness(0x80).unwrap();
+        ili9341.set_cabc_mode(CabcMode::StillPicture).unwrap();
+        ili9341.set_cabc_min_brightness(0x10).unwrap();
+
+        assert_eq!(ili9341.read_brightness().unwrap(), 0x80);
+        let control = ili9341.read_control_display().unwrap();
+        assert!(control.bctrl && control.dd && control.bl);
+        assert_eq!(ili9341.read_cabc_mode().unwrap(), CabcMode::StillPicture);
+        assert_eq!(ili9341.read_cabc_min_brightness().unwrap(), 0x10);
+    }
+
+    #[test]
+    fn gram_drawing() {
+        let mut ili9341 = init();
+        // GRAM belongs to the RGB interface until switched to MCU mode
+        assert!(ili9341.fill_rect(10, 20, 5, 3, Rgb565::RED).is_err());
+        ili9341.set_interface_mode(InterfaceMode::Mcu).unwrap();
+        ili9341.fill_rect(10, 20, 5, 3, Rgb565::RED).unwrap();
+        assert!(ili9341.fill_rect(236, 0, 5, 1, Rgb565::RED).is_err());
+
+        ili9341.set_pixel_format(PixelFormatSet { dpi: 0b110, dbi: 0b101 }).unwrap();
+        ili9341.set_orientation(Orientation::Landscape).unwrap();
+        ili9341.fill_rect(300, 0, 20, 2, Rgb565::new(0, 0xff, 0)).unwrap();
+        ili9341.set_window(0, 0, 1, 0).unwrap();
+        ili9341.write_pixels([Rgb565::BLUE, Rgb565::WHITE]).unwrap();
+
+        let (sim, _) = ili9341.release();
+        assert_eq!(count(&sim, 0xf800), 15);
+        assert_eq!(count(&sim, 0x07e0), 40);
+        assert_eq!(count(&sim, 0x001f), 1);
+    }
+
+    #[test]
+    fn interface_mode() {
+        let mut ili9341 = init();
+        assert_eq!(ili9341.interface_mode(), InterfaceMode::RgbDe);
+        ili9341.set_interface_mode(InterfaceMode::Mcu).unwrap();
+        assert_eq!(ili9341.release().0.reg(Driver::LCD_INTERFACE), &[0x01, 0x00, 0x00]);
+
+        let mut ili9341 = init();
+        let porch = BlankingPorchControl { vfp: 4, vbp: 2, hfp: 10, hbp: 20 };
+        ili9341.set_interface_mode(InterfaceMode::RgbSync(porch)).unwrap();
+
+        let (sim, _) = ili9341.release();
+        assert!(sim.rgb_interface);
+        assert_eq!(sim.reg(Driver::LCD_RGB_INTERFACE), &[0xe2]);
+        assert_eq!(sim.reg(Driver::LCD_BPC), &[4, 2, 10, 20]);
+    }
+
+    #[test]
+    fn read_pixels() {
+        let mut ili9341 = init();
+        let window = Window { x0: 5, y0: 6, x1: 9, y1: 10 };
+        let mut buf = [Rgb565::BLACK; 25];
+        assert!(ili9341.read_pixels(window, &mut buf).is_err());
+
+        ili9341.set_interface_mode(InterfaceMode::Mcu).unwrap();
+        ili9341.set_orientation(Orientation::Landscape).unwrap();
+        let pixels: Vec<_> = (0..25u16).map(|i| Rgb565(i * 0x0841)).collect();
+        ili9341.set_window(5, 6, 9, 10).unwrap();
+        ili9341.write_pixels(pixels.iter().copied()).unwrap();
+        ili9341.read_pixels(window, &mut buf).unwrap();
+        assert_eq!(buf[..], pixels[..]);
+    }
+
+    #[test]
+    fn gamma() {
+        let mut ili9341 = init();
+        ili9341.set_gamma(&GammaCurve::ADAFRUIT).unwrap();
+        let mut bad = GammaCurve::ADAFRUIT;
+        bad.positive.params[0] = 0x10;
+        assert!(ili9341.set_gamma(&bad).is_err());
+
+        let table1 = DigitalGamma1 { params: [0x11; 16] };
+        let table2 = DigitalGamma2 { params: [0x22; 64] };
+        ili9341.set_digital_gamma(&table1, &table2).unwrap();
+
+        let (sim, _) = ili9341.release();
+        assert_eq!(sim.positive_gamma, GammaCurve::ADAFRUIT.positive.params);
+        assert_eq!(sim.negative_gamma, GammaCurve::ADAFRUIT.negative.params);
+        assert!(sim.digital_gamma);
+        assert_eq!(sim.reg(Driver::LCD_DGAMCTRL1), &[0x11; 16]);
+    }
+}
@@ -0,0 +1,270 @@
+use embedded_hal::{ digital::OutputPin, spi::SpiBus };
+
+use super::ili9341::Interface;
+
+/// Transport failure, either on the SPI bus or on one of the control pins
+#[derive(Debug)]
+pub enum LcdError<S, P> {
+    Spi(S),
+    Pin(P),
+}
+
+/// 4-wire serial interface of the ILI9341
+///
+/// `ncs` is driven manually around every transfer and `wrx` acts as the D/CX line
+/// (low for a command byte, high for a parameter byte), so the bus is taken as a raw
+/// `SpiBus` rather than an `SpiDevice`.
+#[allow(unused)]
+pub struct LCD<SPI, NCS, RDX, WRX> {
+    spi: SPI,
+    ncs: NCS,
+    rdx: RDX,
+    wrx: WRX,
+}
+
+impl<SPI, NCS, RDX, WRX> LCD<SPI, NCS, RDX, WRX>
+    where
+        SPI: SpiBus,
+        NCS: OutputPin,
+        RDX: OutputPin<Error = NCS::Error>,
+        WRX: OutputPin<Error = NCS::Error>
+{
+    pub fn new(spi: SPI, ncs: NCS, rdx: RDX, wrx: WRX) -> Self {
+        Self { spi, ncs, rdx, wrx }
+    }
+
+    pub fn release(self) -> (SPI, NCS, RDX, WRX) {
+        (self.spi, self.ncs, self.rdx, self.wrx)
+    }
+}
+
+impl<SPI, NCS, RDX, WRX> Interface
+    for LCD<SPI, NCS, RDX, WRX>
+    where
+        SPI: SpiBus,
+        NCS: OutputPin,
+        RDX: OutputPin<Error = NCS::Error>,
+        WRX: OutputPin<Error = NCS::Error>
+{
+    type Error = LcdError<SPI::Error, NCS::Error>;
+
+    fn write_reg(&mut self, reg: u8) -> Result<(), Self::Error> {
+        self.wrx.set_low().map_err(LcdError::Pin)?;
+        self.ncs.set_low().map_err(LcdError::Pin)?;
+        self.spi.write(&[reg]).map_err(LcdError::Spi)?;
+        self.spi.flush().map_err(LcdError::Spi)?;
+        self.ncs.set_high().map_err(LcdError::Pin)
+    }
+
+    fn write_data(&mut self, data: u8) -> Result<(), Self::Error> {
+        self.wrx.set_high().map_err(LcdError::Pin)?;
+        self.ncs.set_low().map_err(LcdError::Pin)?;
+        self.spi.write(&[data]).map_err(LcdError::Spi)?;
+        self.spi.flush().map_err(LcdError::Spi)?;
+        self.ncs.set_high().map_err(LcdError::Pin)
+    }
+
+    fn write_data_slice(&mut self, data: &[u8]) -> Result<(), Self::Error> {
+        self.wrx.set_high().map_err(LcdError::Pin)?;
+        self.ncs.set_low().map_err(LcdError::Pin)?;
+        self.spi.write(data).map_err(LcdError::Spi)?;
+        self.spi.flush().map_err(LcdError::Spi)?;
+        self.ncs.set_high().map_err(LcdError::Pin)
+    }
+
+    fn read_data(&mut self, reg: u8, buf: &mut [u8]) -> Result<(), Self::Error> {
+        self.wrx.set_low().map_err(LcdError::Pin)?;
+        self.ncs.set_low().map_err(LcdError::Pin)?;
+        self.spi.write(&[reg]).map_err(LcdError::Spi)?;
+        self.spi.flush().map_err(LcdError::Spi)?;
+        self.wrx.set_high().map_err(LcdError::Pin)?;
+        self.spi.read(buf).map_err(LcdError::Spi)?;
+        self.spi.flush().map_err(LcdError::Spi)?;
+        self.ncs.set_high().map_err(LcdError::Pin)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{ cell::RefCell, rc::Rc };
+
+    use embedded_hal::{ digital, spi };
+
+    use super::*;
+    use crate::drivers::{
+        ili9341::{ Ili9341Error, Verify, ILI9341 },
+        ili9341_sim::NoDelay,
+    };
+
+    /// What happened on the bus, in order
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    enum Event {
+        Ncs(bool),
+        Dcx(bool),
+        Rdx(bool),
+        Write(Vec<u8>),
+        Read(usize),
+        Flush,
+    }
+
+    type Log = Rc<RefCell<Vec<Event>>>;
+
+    /// SPI bus logging every transfer and answering reads with `response`, writes fail
+    /// once `fail` is set
+    struct MockSpi {
+        log: Log,
+        response: Vec<u8>,
+        fail: bool,
+    }
+
+    impl spi::ErrorType for MockSpi {
+        type Error = spi::ErrorKind;
+    }
+
+    impl SpiBus for MockSpi {
+        fn read(&mut self, words: &mut [u8]) -> Result<(), spi::ErrorKind> {
+            self.log.borrow_mut().push(Event::Read(words.len()));
+            let len = words.len().min(self.response.len());
+            words[..len].copy_from_slice(&self.response[..len]);
+            Ok(())
+        }
+
+        fn write(&mut self, words: &[u8]) -> Result<(), spi::ErrorKind> {
+            if self.fail {
+                return Err(spi::ErrorKind::Overrun);
+            }
+            self.log.borrow_mut().push(Event::Write(words.to_vec()));
+            Ok(())
+        }
+
+        fn transfer(&mut self, read: &mut [u8], write: &[u8]) -> Result<(), spi::ErrorKind> {
+            self.write(write)?;
+            self.read(read)
+        }
+
+        fn transfer_in_place(&mut self, words: &mut [u8]) -> Result<(), spi::ErrorKind> {
+            self.write(words)?;
+            self.read(words)
+        }
+
+        fn flush(&mut self) -> Result<(), spi::ErrorKind> {
+            self.log.borrow_mut().push(Event::Flush);
+            Ok(())
+        }
+    }
+
+    /// Output pin logging its level as the event `event` makes of it, failing once `fail`
+    /// is set
+    struct MockPin {
+        log: Log,
+        event: fn(bool) -> Event,
+        fail: bool,
+    }
+
+    impl MockPin {
+        fn set(&mut self, level: bool) -> Result<(), digital::ErrorKind> {
+            if self.fail {
+                return Err(digital::ErrorKind::Other);
+            }
+            self.log.borrow_mut().push((self.event)(level));
+            Ok(())
+        }
+    }
+
+    impl digital::ErrorType for MockPin {
+        type Error = digital::ErrorKind;
+    }
+
+    impl OutputPin for MockPin {
+        fn set_low(&mut self) -> Result<(), digital::ErrorKind> {
+            self.set(false)
+        }
+
+        fn set_high(&mut self) -> Result<(), digital::ErrorKind> {
+            self.set(true)
+        }
+    }
+
+    fn lcd(response: &[u8]) -> (LCD<MockSpi, MockPin, MockPin, MockPin>, Log) {
+        let log = Log::default();
+        let pin = |event| MockPin { log: log.clone(), event, fail: false };
+        let spi = MockSpi { log: log.clone(), response: response.to_vec(), fail: false };
+
+        (LCD::new(spi, pin(Event::Ncs), pin(Event::Rdx), pin(Event::Dcx)), log)
+    }
+
+    #[test]
+    fn write_reg() {
+        let (mut lcd, log) = lcd(&[]);
+        lcd.write_reg(0x36).unwrap();
+
+        assert_eq!(*log.borrow(), [
+            Event::Dcx(false),
+            Event::Ncs(false),
+            Event::Write(vec![0x36]),
+            Event::Flush,
+            Event::Ncs(true),
+        ]);
+    }
+
+    #[test]
+    fn write_data() {
+        let (mut lcd, log) = lcd(&[]);
+        lcd.write_data(0xc8).unwrap();
+        lcd.write_data_slice(&[0x00, 0xef]).unwrap();
+
+        assert_eq!(*log.borrow(), [
+            Event::Dcx(true),
+            Event::Ncs(false),
+            Event::Write(vec![0xc8]),
+            Event::Flush,
+            Event::Ncs(true),
+            // A run of parameters goes out within one chip select
+            Event::Dcx(true),
+            Event::Ncs(false),
+            Event::Write(vec![0x00, 0xef]),
+            Event::Flush,
+            Event::Ncs(true),
+        ]);
+    }
+
+    #[test]
+    fn read_data() {
+        let (mut lcd, log) = lcd(&[0x00, 0xc8]);
+        let mut buf = [0; 2];
+        lcd.read_data(0x0b, &mut buf).unwrap();
+
+        assert_eq!(buf, [0x00, 0xc8]);
+        // Command and reply share the chip select, D/CX goes high for the reply
+        assert_eq!(*log.borrow(), [
+            Event::Dcx(false),
+            Event::Ncs(false),
+            Event::Write(vec![0x0b]),
+            Event::Flush,
+            Event::Dcx(true),
+            Event::Read(2),
+            Event::Flush,
+            Event::Ncs(true),
+        ]);
+    }
+
+    #[test]
+    fn init_errors() {
+        // The transport errors reach the caller of `init` as they are, bus or pin
+        let (mut bus_down, _) = lcd(&[]);
+        bus_down.spi.fail = true;
+        let result = ILI9341::init(bus_down, NoDelay, Verify::None);
+        assert!(matches!(
+            result,
+            Err(Ili9341Error::Interface(LcdError::Spi(spi::ErrorKind::Overrun)))
+        ));
+
+        let (mut pin_down, _) = lcd(&[]);
+        pin_down.wrx.fail = true;
+        let result = ILI9341::init(pin_down, NoDelay, Verify::None);
+        assert!(matches!(
+            result,
+            Err(Ili9341Error::Interface(LcdError::Pin(digital::ErrorKind::Other)))
+        ));
+    }
+}
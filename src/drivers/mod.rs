@@ -1 +1,5 @@
 pub mod ili9341;
+pub mod ili9341_regs;
+#[cfg(any(test, feature = "sim"))]
+pub mod ili9341_sim;
+pub mod lcd;
@@ -0,0 +1,207 @@
+//! LTDC layers and double buffering
+//!
+//! [`Layer::configure`] sets up either layer from scratch, which the HAL only does for
+//! layer 1, so layer 2 can carry an overlay with its own window, format and blending.
+//! Indexed layers (L8, AL44, AL88) look their colors up in a [`Palette`] loaded into the
+//! layer's CLUT, and a color key makes one color of a layer transparent.
+//!
+//! One buffer is scanned out while the other is drawn into; [`FrameBuffer::swap`] hands
+//! the finished frame to the LTDC through its shadow registers, which only reload during
+//! vertical blanking, so a frame is never shown half drawn.
+
+use core::ptr;
+
+use example_ili9341::{
+    drivers::ili9341::{ PanelTiming, Rgb565 },
+    graphics::{ Blending, DoubleBuffer, Palette, PixelFormat },
+};
+
+use crate::vsync;
+
+const LTDC_BASE: usize = 0x4001_6800;
+/// Shadow reload configuration register
+const LTDC_SRCR: *mut u32 = (LTDC_BASE + 0x24) as *mut u32;
+/// Reload the shadow registers immediately
+const SRCR_IMR: u32 = 1 << 0;
+/// Reload the shadow registers at the next vertical blanking
+const SRCR_VBR: u32 = 1 << 1;
+/// Current display status register
+const LTDC_CDSR: *const u32 = (LTDC_BASE + 0x48) as *const u32;
+/// Vertical data enable, clear during vertical blanking
+const CDSR_VDES: u32 = 1 << 0;
+
+/// Layer register offsets, layer 1
+const LTDC_L1CR: usize = 0x84;
+const LTDC_L1WHPCR: usize = 0x88;
+const LTDC_L1WVPCR: usize = 0x8c;
+const LTDC_L1CKCR: usize = 0x90;
+const LTDC_L1PFCR: usize = 0x94;
+const LTDC_L1CACR: usize = 0x98;
+const LTDC_L1DCCR: usize = 0x9c;
+const LTDC_L1BFCR: usize = 0xa0;
+/// Color frame buffer address register, layer 1 offset
+const LTDC_L1CFBAR: usize = 0xac;
+const LTDC_L1CFBLR: usize = 0xb0;
+const LTDC_L1CFBLNR: usize = 0xb4;
+const LTDC_L1CLUTWR: usize = 0xc4;
+
+/// Layer enable
+const CR_LEN: u32 = 1 << 0;
+/// Color keying enable
+const CR_COLKEN: u32 = 1 << 1;
+/// Color look-up table enable
+const CR_CLUTEN: u32 = 1 << 4;
+
+/// One of the two LTDC layers
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Layer {
+    Layer1,
+    Layer2,
+}
+
+impl Layer {
+    /// Address of the register at `offset` in the layer 1 block, moved to this layer
+    pub(crate) const fn reg(self, offset: usize) -> *mut u32 {
+        let layer = match self {
+            Layer::Layer1 => 0x00,
+            Layer::Layer2 => 0x80,
+        };
+
+        (LTDC_BASE + offset + layer) as *mut u32
+    }
+
+    /// Set the layer up as described by `config` and enable it, scanning out `address`
+    ///
+    /// Takes effect immediately; meant for start-up or while the layer is disabled. The
+    /// CLUT is enabled for indexed formats, load it with [`load_palette`](Self::load_palette).
+    pub fn configure(self, timing: &PanelTiming, config: &LayerConfig, address: *const u8) {
+        let line_bytes = config.width as u32 * config.format.bytes_per_pixel();
+        let mut cr = CR_LEN;
+        if config.format.is_indexed() {
+            cr |= CR_CLUTEN;
+        }
+        if config.color_key.is_some() {
+            cr |= CR_COLKEN;
+        }
+
+        unsafe {
+            self.write_window(timing, config.x, config.y, config.width, config.height);
+            ptr::write_volatile(self.reg(LTDC_L1PFCR), config.format as u32);
+            ptr::write_volatile(self.reg(LTDC_L1CACR), config.alpha as u32);
+            ptr::write_volatile(self.reg(LTDC_L1DCCR), config.default_color);
+            ptr::write_volatile(self.reg(LTDC_L1BFCR), config.blending.bfcr());
+            ptr::write_volatile(self.reg(LTDC_L1CFBAR), address as u32);
+            // Pitch, then the line length plus the 3 bytes the LTDC FIFO needs
+            ptr::write_volatile(self.reg(LTDC_L1CFBLR), (line_bytes << 16) | (line_bytes + 3));
+            ptr::write_volatile(self.reg(LTDC_L1CFBLNR), config.height as u32);
+            ptr::write_volatile(self.reg(LTDC_L1CKCR), config.color_key.unwrap_or(0));
+            ptr::write_volatile(self.reg(LTDC_L1CR), cr);
+        }
+
+        reload(SRCR_IMR);
+    }
+
+    /// Load `palette` into the layer's CLUT
+    ///
+    /// The CLUT may only be written while the layer is disabled or during blanking, so
+    /// for an enabled layer this waits for vertical blanking unless it is already under
+    /// way; call it right after [`vsync::wait_for_vblank`](crate::vsync::wait_for_vblank)
+    /// for the whole blanking period. Cycling palette entries this way animates an
+    /// indexed layer without redrawing it.
+    pub fn load_palette(self, palette: &Palette) {
+        unsafe {
+            if ptr::read_volatile(self.reg(LTDC_L1CR)) & CR_LEN != 0 {
+                while ptr::read_volatile(LTDC_CDSR) & CDSR_VDES != 0 {}
+            }
+
+            for (index, color) in palette.0.iter().enumerate() {
+                ptr::write_volatile(
+                    self.reg(LTDC_L1CLUTWR),
+                    ((index as u32) << 24) | (color & 0x00ff_ffff)
+                );
+            }
+        }
+    }
+
+    /// Window registers count from the start of the sync pulse, the active area begins
+    /// after sync and back porch
+    unsafe fn write_window(self, timing: &PanelTiming, x: u16, y: u16, width: u16, height: u16) {
+        let h_start = (timing.hsync + timing.hbp + x) as u32;
+        let v_start = (timing.vsync + timing.vbp + y) as u32;
+
+        ptr::write_volatile(
+            self.reg(LTDC_L1WHPCR),
+            ((h_start + width as u32 - 1) << 16) | h_start
+        );
+        ptr::write_volatile(
+            self.reg(LTDC_L1WVPCR),
+            ((v_start + height as u32 - 1) << 16) | v_start
+        );
+    }
+}
+
+/// Request a shadow register reload, `SRCR_IMR` or `SRCR_VBR`
+fn reload(mode: u32) {
+    unsafe {
+        ptr::write_volatile(LTDC_SRCR, mode);
+    }
+}
+
+/// Layer window, format and blending, window coordinates relative to the active area
+#[derive(Clone, Copy, Debug)]
+pub struct LayerConfig {
+    pub x: u16,
+    pub y: u16,
+    pub width: u16,
+    pub height: u16,
+    pub format: PixelFormat,
+    /// Constant alpha, 255 is opaque
+    pub alpha: u8,
+    pub blending: Blending,
+    /// ARGB8888 color shown outside the window
+    pub default_color: u32,
+    /// RGB888 color made transparent, if any
+    pub color_key: Option<u32>,
+}
+
+/// Two RGB565 buffers taking turns on one LTDC layer
+///
+/// The layer itself (window, pixel format, blending) is set up through the LTDC driver
+/// with [`front_ptr`](Self::front_ptr) as its start address; this only moves the start
+/// address afterwards. The LTDC has to be running, with [`vsync::init`] done, for
+/// [`swap`](Self::swap) to return.
+pub struct FrameBuffer {
+    layer: Layer,
+    buffers: DoubleBuffer<'static, Rgb565>,
+}
+
+impl FrameBuffer {
+    pub fn new(layer: Layer, buffers: DoubleBuffer<'static, Rgb565>) -> Self {
+        Self { layer, buffers }
+    }
+
+    /// Start of the buffer being scanned out
+    pub fn front_ptr(&self) -> *const Rgb565 {
+        self.buffers.front().as_ptr()
+    }
+
+    /// The buffers, to draw the next frame into the back one
+    pub fn buffers(&mut self) -> &mut DoubleBuffer<'static, Rgb565> {
+        &mut self.buffers
+    }
+
+    /// Show the back buffer from the next frame on and start drawing into the other one
+    ///
+    /// Sleeps until the LTDC has picked up the new address at vertical blanking, since
+    /// the old front buffer is scanned out until then.
+    pub fn swap(&mut self) {
+        let reloads = vsync::reload_count();
+        unsafe {
+            ptr::write_volatile(self.layer.reg(LTDC_L1CFBAR), self.buffers.back().as_ptr() as u32);
+        }
+        reload(SRCR_VBR);
+        vsync::wait_for_reload(reloads);
+
+        self.buffers.flip();
+    }
+}
@@ -0,0 +1,277 @@
+//! Pixel formats, blending, palettes and buffers of the LTDC layers
+//!
+//! Plain values, encoded the way the layer registers and the CLUT take them, and the
+//! pixel buffers a layer scans out; programming the registers is left to the firmware.
+
+use core::ops::RangeInclusive;
+
+use crate::drivers::ili9341::Rgb565;
+
+/// Layer pixel formats, as encoded in LxPFCR
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PixelFormat {
+    ARGB8888 = 0b000,
+    RGB888 = 0b001,
+    RGB565 = 0b010,
+    ARGB1555 = 0b011,
+    ARGB4444 = 0b100,
+    L8 = 0b101,
+    AL44 = 0b110,
+    AL88 = 0b111,
+}
+
+impl PixelFormat {
+    /// Formats whose pixels index the CLUT
+    pub const fn is_indexed(self) -> bool {
+        matches!(self, PixelFormat::L8 | PixelFormat::AL44 | PixelFormat::AL88)
+    }
+
+    pub const fn bytes_per_pixel(self) -> u32 {
+        match self {
+            PixelFormat::ARGB8888 => 4,
+            PixelFormat::RGB888 => 3,
+            PixelFormat::RGB565 | PixelFormat::ARGB1555 | PixelFormat::ARGB4444 => 2,
+            PixelFormat::AL88 => 2,
+            PixelFormat::L8 | PixelFormat::AL44 => 1,
+        }
+    }
+}
+
+/// How a layer is blended onto what lies below it
+///
+/// The layer contributes `alpha * layer` and the layers below `(1 - alpha) * below`,
+/// where `alpha` is either the constant alpha or the pixel's own alpha scaled by it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Blending {
+    /// Constant alpha only, for formats without an alpha channel
+    ConstantAlpha,
+    /// Pixel alpha times constant alpha, for ARGB and AL formats
+    PixelAlpha,
+}
+
+impl Blending {
+    /// Blending factors as encoded in LxBFCR
+    pub const fn bfcr(self) -> u32 {
+        match self {
+            Blending::ConstantAlpha => (0b100 << 8) | 0b101,
+            Blending::PixelAlpha => (0b110 << 8) | 0b111,
+        }
+    }
+}
+
+/// 256 RGB888 colors (`0x00rrggbb`) for the CLUT of an indexed layer
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Palette(pub [u32; 256]);
+
+impl Palette {
+    /// Index `i` maps to the gray level `i`
+    pub const fn grayscale() -> Self {
+        let mut colors = [0; 256];
+        let mut i = 0;
+        while i < 256 {
+            colors[i] = (i as u32) * 0x01_01_01;
+            i += 1;
+        }
+
+        Self(colors)
+    }
+
+    /// Same color as `color` stored in RGB565, with the low bits filled in
+    pub const fn rgb888(color: Rgb565) -> u32 {
+        let r = ((color.0 >> 11) & 0x1f) as u32;
+        let g = ((color.0 >> 5) & 0x3f) as u32;
+        let b = (color.0 & 0x1f) as u32;
+
+        ((r << 3 | r >> 2) << 16) | ((g << 2 | g >> 4) << 8) | (b << 3 | b >> 2)
+    }
+
+    pub fn get(&self, index: u8) -> u32 {
+        self.0[index as usize]
+    }
+
+    pub fn set(&mut self, index: u8, color: u32) {
+        self.0[index as usize] = color;
+    }
+
+    /// Shift the entries in `range` one step towards its start, wrapping the first entry
+    /// around to the end: one step of a palette-cycling animation
+    ///
+    /// An empty range, its start past its end, leaves the palette as it is.
+    pub fn rotate(&mut self, range: RangeInclusive<u8>) {
+        let (start, end) = range.into_inner();
+        if start > end {
+            return;
+        }
+
+        self.0[start as usize..=end as usize].rotate_left(1);
+    }
+}
+
+/// Two buffers of `width` x `height` pixels, row by row, one shown while the other is
+/// drawn into
+///
+/// Drawing goes to the back buffer, anything outside the buffer is clipped.
+pub struct DoubleBuffer<'a, P> {
+    width: usize,
+    height: usize,
+    front: &'a mut [P],
+    back: &'a mut [P],
+}
+
+impl<'a, P: Copy> DoubleBuffer<'a, P> {
+    /// `front` is shown first and `back` drawn into
+    pub fn new(width: usize, height: usize, front: &'a mut [P], back: &'a mut [P]) -> Self {
+        assert!(front.len() == width * height && back.len() == width * height);
+
+        Self { width, height, front, back }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Buffer being shown
+    pub fn front(&self) -> &[P] {
+        self.front
+    }
+
+    /// Buffer to draw the next frame into
+    pub fn back(&mut self) -> &mut [P] {
+        self.back
+    }
+
+    /// Offset of pixel `x`, `y` into either buffer, `None` outside of them
+    pub fn index(&self, x: usize, y: usize) -> Option<usize> {
+        if x < self.width && y < self.height {
+            Some(y * self.width + x)
+        } else {
+            None
+        }
+    }
+
+    pub fn set_pixel(&mut self, x: usize, y: usize, color: P) {
+        if let Some(index) = self.index(x, y) {
+            self.back[index] = color;
+        }
+    }
+
+    /// Fill the `width` x `height` rectangle at `x`, `y`, clipped to the buffer
+    pub fn fill_rect(&mut self, x: usize, y: usize, width: usize, height: usize, color: P) {
+        let columns = x.min(self.width)..x.saturating_add(width).min(self.width);
+        let rows = y.min(self.height)..y.saturating_add(height).min(self.height);
+        for row in self.back.chunks_exact_mut(self.width).take(rows.end).skip(rows.start) {
+            row[columns.clone()].fill(color);
+        }
+    }
+
+    pub fn clear(&mut self, color: P) {
+        self.back.fill(color);
+    }
+
+    /// Exchange the buffers once the back buffer is being shown
+    pub fn flip(&mut self) {
+        core::mem::swap(&mut self.front, &mut self.back);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pixel_formats() {
+        assert_eq!(PixelFormat::RGB565 as u32, 0b010);
+        assert_eq!(PixelFormat::RGB565.bytes_per_pixel(), 2);
+        assert_eq!(PixelFormat::AL88.bytes_per_pixel(), 2);
+        assert_eq!(PixelFormat::L8.bytes_per_pixel(), 1);
+        assert!(PixelFormat::AL44.is_indexed());
+        assert!(!PixelFormat::ARGB4444.is_indexed());
+    }
+
+    #[test]
+    fn blending_factors() {
+        // BF1 constant alpha, BF2 1 - constant alpha
+        assert_eq!(Blending::ConstantAlpha.bfcr(), 0x0405);
+        // BF1 pixel alpha x constant alpha, BF2 1 - pixel alpha x constant alpha
+        assert_eq!(Blending::PixelAlpha.bfcr(), 0x0607);
+    }
+
+    #[test]
+    fn palette_colors() {
+        assert_eq!(Palette::rgb888(Rgb565::WHITE), 0x00ff_ffff);
+        assert_eq!(Palette::rgb888(Rgb565::BLACK), 0);
+        assert_eq!(Palette::rgb888(Rgb565::RED), 0x00ff_0000);
+        assert_eq!(Palette::rgb888(Rgb565::new(0x84, 0x82, 0x84)), 0x0084_8284);
+
+        let palette = Palette::grayscale();
+        assert_eq!(palette.get(0x00), 0);
+        assert_eq!(palette.get(0x80), 0x0080_8080);
+        assert_eq!(palette.get(0xff), 0x00ff_ffff);
+    }
+
+    #[test]
+    fn palette_rotate() {
+        let mut palette = Palette::grayscale();
+        palette.rotate(0x10..=0x13);
+        assert_eq!(palette.0[0x0f..=0x14], [0x0f, 0x11, 0x12, 0x13, 0x10, 0x14].map(gray));
+
+        // The whole palette, and a single entry staying put
+        palette.rotate(0x00..=0xff);
+        assert_eq!((palette.get(0x00), palette.get(0xff)), (gray(0x01), gray(0x00)));
+        palette.rotate(0x80..=0x80);
+        assert_eq!(palette.get(0x80), gray(0x81));
+
+        // Empty ranges are no-ops
+        let before = palette.clone();
+        #[allow(clippy::reversed_empty_ranges)]
+        palette.rotate(0x20..=0x1f);
+        assert_eq!(palette, before);
+    }
+
+    fn gray(level: u32) -> u32 {
+        level * 0x01_01_01
+    }
+
+    #[test]
+    fn buffer_index() {
+        let (mut front, mut back) = ([0u8; 12], [0u8; 12]);
+        let buffers = DoubleBuffer::new(4, 3, &mut front, &mut back);
+
+        assert_eq!(buffers.index(0, 0), Some(0));
+        assert_eq!(buffers.index(3, 0), Some(3));
+        assert_eq!(buffers.index(1, 2), Some(9));
+        assert_eq!(buffers.index(4, 0), None);
+        assert_eq!(buffers.index(0, 3), None);
+    }
+
+    #[test]
+    fn buffer_drawing() {
+        let (mut front, mut back) = ([0u8; 12], [0u8; 12]);
+        let mut buffers = DoubleBuffer::new(4, 3, &mut front, &mut back);
+
+        buffers.set_pixel(3, 2, 1);
+        // Off the right edge, which must not wrap to the next row, and below the bottom
+        buffers.set_pixel(4, 1, 2);
+        buffers.set_pixel(0, 3, 2);
+        buffers.set_pixel(usize::MAX, usize::MAX, 2);
+        assert_eq!(buffers.back(), [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]);
+
+        // Clipped at the right and bottom edges
+        buffers.fill_rect(2, 1, 5, usize::MAX, 3);
+        assert_eq!(buffers.back(), [0, 0, 0, 0, 0, 0, 3, 3, 0, 0, 3, 3]);
+        buffers.fill_rect(4, 0, 2, 2, 4);
+        assert_eq!(buffers.back(), [0, 0, 0, 0, 0, 0, 3, 3, 0, 0, 3, 3]);
+
+        // Drawing only ever touches the back buffer
+        assert_eq!(buffers.front(), [0; 12]);
+        buffers.flip();
+        assert_eq!(buffers.front(), [0, 0, 0, 0, 0, 0, 3, 3, 0, 0, 3, 3]);
+        buffers.clear(5);
+        assert_eq!(buffers.back(), [5; 12]);
+        assert_eq!(buffers.front()[6], 3);
+    }
+}
@@ -1,5 +1,5 @@
 // pub const IMAGE: [u8; 320 * 240 * 2] = [0; 320 * 240 * 2];
-pub const IMAGE: [u8; 320 * 240 * 2] = [
+pub static IMAGE: [u8; 320 * 240 * 2] = [
     0x9f, 0x86, 0x9f, 0x86, 0x9f, 0x86, 0x9f, 0x86, 0x9f, 0x86, 0x9f, 0x86, 0x7e, 0x86, 0x3e, 0x7e,
     0x1e, 0x7e, 0xfd, 0x75, 0xdd, 0x6d, 0xbd, 0x65, 0x9d, 0x65, 0x7c, 0x5d, 0x7d, 0x5d, 0x7d, 0x5d,
     0x7d, 0x5d, 0x7d, 0x5d, 0x7d, 0x5d, 0x7d, 0x5d, 0x5d, 0x5d, 0x5d, 0x5d, 0x5d, 0x5d, 0x5d, 0x5d,
@@ -0,0 +1,16 @@
+//! Firmware parts that build without the HAL
+//!
+//! The ILI9341 driver only depends on `embedded-hal`, the clock tree arithmetic and the
+//! LTDC layer formats and buffers on nothing at all and the SDRAM bring-up programs the
+//! FMC through raw registers, so all of them build for the host as well as for the
+//! board; `cargo test-host` runs the unit tests.
+//!
+//! The `sim` feature exposes the host-side controller model, `drivers::ili9341_sim`, to
+//! integration tests and other crates; it needs std, so it is for the host only.
+
+#![cfg_attr(not(any(test, feature = "sim")), no_std)]
+
+pub mod clocks;
+pub mod drivers;
+pub mod graphics;
+pub mod sdram;
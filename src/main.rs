@@ -1,19 +1,27 @@
 #![no_std]
 #![no_main]
 
+mod framebuffer;
 mod image;
-mod drivers;
+mod systick;
+mod vsync;
 
 extern crate panic_semihosting;
 extern crate stm32_hal as hal;
 
-use core::fmt::Write;
+use core::{ fmt::Write, mem::MaybeUninit, ops::RangeInclusive, ptr };
 
 use cortex_m_rt::entry;
 use cortex_m_semihosting::hio::{ self, HostStream };
-use drivers::ili9341::ILI9341;
+use embedded_hal::digital::{ OutputPin, StatefulOutputPin };
+use example_ili9341::{
+    clocks::{ self, ClockTree, ClockTreeBuilder, PllSaiConfig },
+    drivers::{ ili9341::{ PanelTiming, Rgb565, Verify, ILI9341 }, lcd::LCD },
+    graphics::{ self, Blending, DoubleBuffer, Palette },
+    sdram,
+};
+use framebuffer::{ FrameBuffer, Layer, LayerConfig };
 use hal::{
-    embedded_hal::digital::{ OutputPin, StatefulOutputPin },
     gpio::{ self, pin::{ Output, OutputType, Pull, Speed }, PinMask },
     ltdc::{ self, Color, LTDCConfig, PixelClockPolarity, PixelFormat, Polarity },
     rcc::{
@@ -39,10 +47,8 @@ use hal::{
         SPIConfig,
         SPI,
     },
-    Peripheral,
-    PeripheralRef,
 };
-use log::{ info, Log };
+use log::{ error, info, Log };
 
 #[entry]
 fn main() -> ! {
@@ -51,93 +57,246 @@ fn main() -> ! {
     init_system_clocks();
     init_ltdc_pins();
 
+    let cp = cortex_m::Peripherals::take().unwrap();
+    let mut delay = systick::init(cp.SYST, SYSCLK_HZ);
+
+    init_sdram_pins();
+    sdram::init(CLOCKS.hclk_hz, &mut delay);
+    info!("SDRAM ready, {} bytes at {:#010x}", sdram::SDRAM_SIZE, sdram::SDRAM_BASE);
+
     let gpiog = gpio::GPIOG::take();
 
     let mut green_led = Output::new(gpiog.pin(13), Speed::High);
 
     green_led.set_high().unwrap();
 
-    let lcd = LCD::init();
-    let _ili9341 = ILI9341::init(lcd);
+    let lcd = init_lcd();
+    // Nothing can be read back on the Discovery board, the panel's SDO is not on SPI5 MISO
+    if let Err(err) = Display::init(lcd, delay, Verify::None) {
+        error!("Failed to initialize display: {:?}", err);
+    }
+
+    // The RGB interface carries the panel's native portrait frame whatever MADCTL says
+    let timing = PanelTiming::DATASHEET;
+    let (width, height) = (PanelTiming::ACTIVE_WIDTH, PanelTiming::ACTIVE_HEIGHT);
+    info!("Panel refresh rate {} Hz", timing.refresh_rate(LCD_CLOCK_HZ));
+
+    let (front, back) = unsafe { frame_buffers() };
+    load_image(front, 0);
+    load_image(back, 0);
+    let buffers = DoubleBuffer::new(width as usize, height as usize, front, back);
+    let mut framebuffer = FrameBuffer::new(Layer::Layer1, buffers);
 
     let ltdc = ltdc::LTDC::take();
-    ltdc.init(LTDCConfig {
-        horizontal_sync_polarity: Polarity::ActiveLow,
-        vertical_sync_polarity: Polarity::ActiveLow,
-        data_enable_polarity: Polarity::ActiveLow,
-        pixel_clock_polarity: PixelClockPolarity::Normal,
-        horizontal_sync: ILI9341::ILI9341_HSYNC,
-        vertical_sync: ILI9341::ILI9341_VSYNC,
-        horizontal_back_porch: ILI9341::ILI9341_HBP,
-        vertical_back_porch: ILI9341::ILI9341_VBP,
-        active_width: ILI9341::ILI9341_LCD_PIXEL_WIDTH as u16,
-        active_height: ILI9341::ILI9341_LCD_PIXEL_HEIGHT as u16,
-        horizontal_front_porch: ILI9341::ILI9341_HFP,
-        vertical_front_porch: ILI9341::ILI9341_VFP,
-        background_color: Color(0, 0, 0, 0),
-    });
+    ltdc.init(ltdc_config(&timing));
 
     ltdc.layer1_configure(
         0,
         0,
-        240,
-        320,
+        width,
+        height,
         PixelFormat::RGB565,
         Color(0, 0, 0, 0),
-        &image::IMAGE as *const _
+        framebuffer.front_ptr() as *const _
+    );
+
+    let status_bar = unsafe { status_bar(width as usize) };
+    draw_status_bar(status_bar, width as usize);
+    let mut palette = status_bar_palette();
+    Layer::Layer2.load_palette(&palette);
+    Layer::Layer2.configure(
+        &timing,
+        &LayerConfig {
+            x: 0,
+            y: 0,
+            width,
+            height: STATUS_BAR_HEIGHT as u16,
+            format: graphics::PixelFormat::L8,
+            alpha: 0xa0,
+            blending: Blending::ConstantAlpha,
+            default_color: 0,
+            color_key: Some(STATUS_BAR_KEY),
+        },
+        status_bar.as_ptr()
     );
 
+    vsync::init(&timing, height);
+    info!("Measured refresh rate {} Hz", measure_refresh_rate());
+    let heartbeat_frames = timing.refresh_rate(LCD_CLOCK_HZ) * HEARTBEAT_MS / 1_000;
+
+    let mut last_beat = vsync::frame_count() / heartbeat_frames;
+    let mut position = 0;
     loop {
-        for _ in 0..200_000 {
+        draw_frame(framebuffer.buffers(), position);
+        // Returns as the new frame is picked up, at the start of vertical blanking
+        framebuffer.swap();
+        position = position.wrapping_add(1);
+        palette.rotate(CYCLE_INDICES);
+
+        let beat = vsync::frame_count() / heartbeat_frames;
+        if beat != last_beat {
+            last_beat = beat;
+            green_led.toggle().unwrap();
+            let heartbeat = match palette.get(HEARTBEAT_INDEX) {
+                STATUS_BAR_KEY => Palette::rgb888(Rgb565::WHITE),
+                _ => STATUS_BAR_KEY,
+            };
+            palette.set(HEARTBEAT_INDEX, heartbeat);
         }
 
-        green_led.toggle().unwrap();
+        Layer::Layer2.load_palette(&palette);
+    }
+}
+
+/// Half period of the heartbeat LED
+const HEARTBEAT_MS: u32 = 500;
+
+/// Frames timed by [`measure_refresh_rate`], about a second
+const MEASURED_FRAMES: u32 = 64;
+
+/// Time [`MEASURED_FRAMES`] frames against SysTick, to confirm the LTDC runs at the rate
+/// the clock tree predicts
+fn measure_refresh_rate() -> u32 {
+    vsync::wait_for_vblank();
+    let start = systick::millis();
+    for _ in 0..MEASURED_FRAMES {
+        vsync::wait_for_vblank();
     }
+    let elapsed_ms = systick::millis().wrapping_sub(start).max(1);
+
+    (MEASURED_FRAMES * 1_000 + elapsed_ms / 2) / elapsed_ms
 }
 
+/// Clock tree set up by [`init_system_clocks`], 72 MHz SYSCLK with 48 MHz for USB
+const CLOCKS: ClockTree = match ClockTreeBuilder::new(clocks::HSE_HZ)
+    .sysclk(72_000_000)
+    .usb()
+    .build()
+{
+    Ok(clocks) => clocks,
+    Err(_) => panic!("Unreachable clock tree!"),
+};
+
+/// SYSCLK as configured by [`init_system_clocks`]
+const SYSCLK_HZ: u32 = CLOCKS.sysclk_hz;
+
+/// PLLSAI, HSE / 4 * 60 / 5 / 4 = 6 MHz pixel clock, 65 Hz with the datasheet timing
+const PLLSAI_CONFIG: PllSaiConfig = PllSaiConfig {
+    pllsain: 60,
+    pllsaiq: 4,
+    pllsair: 5,
+    lcd_div: 4,
+};
+
+/// LTDC pixel clock as configured by [`init_system_clocks`]
+const LCD_CLOCK_HZ: u32 = clocks::pixel_clock_hz(&CLOCKS, &PLLSAI_CONFIG);
+
+// The tree matches the values the board has always run at, and PLLSAI gives a 6 MHz
+// pixel clock.
+const _: () = {
+    assert!(CLOCKS.pllm == 4 && CLOCKS.plln == 72 && CLOCKS.pllp == 2 && CLOCKS.pllq == 3);
+    assert!(CLOCKS.apb1_hz == 36_000_000 && CLOCKS.apb2_hz == 72_000_000);
+    assert!(CLOCKS.pll48_hz == 48_000_000 && CLOCKS.flash_wait_states == 2);
+
+    assert!(LCD_CLOCK_HZ == 6_000_000);
+    assert!(PanelTiming::DATASHEET.refresh_rate(LCD_CLOCK_HZ) == 65);
+};
+
 /// System Clock Configuration
-///   The system Clock is configured as follow :
-///       System Clock source            = PLL (HSE)
-///       SYSCLK(Hz)                     = 72000000
-///       HCLK(Hz)                       = 72000000
-///       AHB Prescaler                  = 1
-///       APB1 Prescaler                 = 2
-///       APB2 Prescaler                 = 1
-///       HSE Frequency(Hz)              = 8000000
-///       PLL_M                          = 4
-///       PLL_N                          = 72
-///       PLL_P                          = 2
-///       PLL_Q                          = 3
-///       VDD(V)                         = 3.3
-///       Main regulator output voltage  = Scale1 mode
-///       Flash Latency(WS)              = 5
-///    The LTDC Clock is configured as follow :
-///       PLLSAIN                        = 50
-///       PLLSAIR                        = 2
-///       PLLSAIDivR                     = 2
+///   The system clock runs from the main PLL off the HSE as computed in [`CLOCKS`],
+///   and the LTDC from PLLSAI as set in [`PLLSAI_CONFIG`]. The resulting tree is
+///   logged at start-up.
 fn init_system_clocks() {
+    set_flash_latency(CLOCKS.flash_wait_states);
+
     let rcc = rcc::RCC::take();
-    rcc.configure_system_clock(
-        SystemClockConfig::PLL(PLLConfig {
-            clock_source: PLLClockSource::HSE,
-            pllm: 4,
-            plln: 72,
-            pllq: 3,
-            system_clock_div_factor: PLLSysClockDivisionFactor::DividedBy2,
-        }),
-        BUSConfig {
-            ahb_prescaler: AHBPrescaler::NotDivided,
-            apb1_prescaler: APBPrescaler::DividedBy2,
-            apb2_prescaler: APBPrescaler::NotDivided,
-        }
-    );
+    rcc.configure_system_clock(SystemClockConfig::PLL(pll_config(&CLOCKS)), bus_config(&CLOCKS));
+
+    rcc.configure_pllsai(pllsai_config(&PLLSAI_CONFIG));
+
+    info!("Clock tree:\n{}", CLOCKS);
+    info!("LCD-TFT clock {} Hz", LCD_CLOCK_HZ);
+}
+
+const fn pll_config(clocks: &ClockTree) -> PLLConfig {
+    PLLConfig {
+        clock_source: PLLClockSource::HSE,
+        pllm: clocks.pllm as _,
+        plln: clocks.plln as _,
+        pllq: clocks.pllq as _,
+        system_clock_div_factor: match clocks.pllp {
+            2 => PLLSysClockDivisionFactor::DividedBy2,
+            4 => PLLSysClockDivisionFactor::DividedBy4,
+            6 => PLLSysClockDivisionFactor::DividedBy6,
+            _ => PLLSysClockDivisionFactor::DividedBy8,
+        },
+    }
+}
+
+const fn bus_config(clocks: &ClockTree) -> BUSConfig {
+    BUSConfig {
+        ahb_prescaler: AHBPrescaler::NotDivided,
+        apb1_prescaler: apb_prescaler(clocks.apb1_div),
+        apb2_prescaler: apb_prescaler(clocks.apb2_div),
+    }
+}
+
+const fn apb_prescaler(div: u32) -> APBPrescaler {
+    match div {
+        1 => APBPrescaler::NotDivided,
+        2 => APBPrescaler::DividedBy2,
+        4 => APBPrescaler::DividedBy4,
+        8 => APBPrescaler::DividedBy8,
+        _ => APBPrescaler::DividedBy16,
+    }
+}
+
+const fn pllsai_config(pllsai: &PllSaiConfig) -> PLLSAIConfig {
+    PLLSAIConfig {
+        pllsain: pllsai.pllsain as _,
+        pllsaiq: pllsai.pllsaiq as _,
+        pllsair: pllsai.pllsair as _,
+        lcd_div_factor: match pllsai.lcd_div {
+            2 => LCDClockDivisionFactor::DividedBy2,
+            4 => LCDClockDivisionFactor::DividedBy4,
+            8 => LCDClockDivisionFactor::DividedBy8,
+            _ => LCDClockDivisionFactor::DividedBy16,
+        },
+    }
+}
+
+/// Flash access control register, RM0090 3.9.1
+const FLASH_ACR: *mut u32 = 0x4002_3c00 as *mut u32;
+
+/// Program the flash wait states for the HCLK about to be configured
+///
+/// Raising the latency ahead of the clock is always safe, so this runs before the
+/// switch to the PLL.
+fn set_flash_latency(wait_states: u32) {
+    unsafe {
+        let acr = core::ptr::read_volatile(FLASH_ACR);
+        core::ptr::write_volatile(FLASH_ACR, (acr & !0xf) | wait_states);
+        while core::ptr::read_volatile(FLASH_ACR) & 0xf != wait_states {}
+    }
+}
 
-    rcc.configure_pllsai(PLLSAIConfig {
-        pllsain: 60, //50,
-        pllsaiq: 4,
-        pllsair: 5, //2,
-        lcd_div_factor: LCDClockDivisionFactor::DividedBy4, //LCDClockDivisionFactor::DividedBy2,
-    })
+/// LTDC configuration for `timing`, with the panel's native active area
+fn ltdc_config(timing: &PanelTiming) -> LTDCConfig {
+    LTDCConfig {
+        horizontal_sync_polarity: Polarity::ActiveLow,
+        vertical_sync_polarity: Polarity::ActiveLow,
+        data_enable_polarity: Polarity::ActiveLow,
+        pixel_clock_polarity: PixelClockPolarity::Normal,
+        horizontal_sync: timing.hsync,
+        vertical_sync: timing.vsync,
+        horizontal_back_porch: timing.hbp,
+        vertical_back_porch: timing.vbp,
+        active_width: PanelTiming::ACTIVE_WIDTH,
+        active_height: PanelTiming::ACTIVE_HEIGHT,
+        horizontal_front_porch: timing.hfp,
+        vertical_front_porch: timing.vfp,
+        background_color: Color(0, 0, 0, 0),
+    }
 }
 
 fn init_ltdc_pins() {
@@ -208,80 +367,270 @@ fn init_ltdc_pins() {
     );
 }
 
-#[allow(unused)]
-pub struct LCD {
-    spi: &'static mut SPI,
-    ncs: Output,
-    rdx: Output,
-    wrx: Output,
+/// Mux the FMC SDRAM pins to AF12
+fn init_sdram_pins() {
+    let gpiob = gpio::GPIOB::take();
+    let gpioc = gpio::GPIOC::take();
+    let gpiod = gpio::GPIOD::take();
+    let gpioe = gpio::GPIOE::take();
+    let gpiof = gpio::GPIOF::take();
+    let gpiog = gpio::GPIOG::take();
+
+    gpiob.enable_clock();
+    gpioc.enable_clock();
+    gpiod.enable_clock();
+    gpioe.enable_clock();
+    gpiof.enable_clock();
+    gpiog.enable_clock();
+
+    // PB5 -> SDCKE1, PB6 -> SDNE1
+    gpiob.init_alternate_pins(
+        PinMask::PIN5 | PinMask::PIN6,
+        OutputType::PushPull,
+        Speed::VeryHigh,
+        Pull::None,
+        12
+    );
+    // PC0 -> SDNWE
+    gpioc.init_alternate_pins(PinMask::PIN0, OutputType::PushPull, Speed::VeryHigh, Pull::None, 12);
+    // PD0/1/8/9/10/14/15 -> D2/D3/D13/D14/D15/D0/D1
+    gpiod.init_alternate_pins(
+        PinMask::PIN0 |
+            PinMask::PIN1 |
+            PinMask::PIN8 |
+            PinMask::PIN9 |
+            PinMask::PIN10 |
+            PinMask::PIN14 |
+            PinMask::PIN15,
+        OutputType::PushPull,
+        Speed::VeryHigh,
+        Pull::None,
+        12
+    );
+    // PE0/1 -> NBL0/1, PE7..15 -> D4..D12
+    gpioe.init_alternate_pins(
+        PinMask::PIN0 |
+            PinMask::PIN1 |
+            PinMask::PIN7 |
+            PinMask::PIN8 |
+            PinMask::PIN9 |
+            PinMask::PIN10 |
+            PinMask::PIN11 |
+            PinMask::PIN12 |
+            PinMask::PIN13 |
+            PinMask::PIN14 |
+            PinMask::PIN15,
+        OutputType::PushPull,
+        Speed::VeryHigh,
+        Pull::None,
+        12
+    );
+    // PF0..5 -> A0..A5, PF11 -> SDNRAS, PF12..15 -> A6..A9
+    gpiof.init_alternate_pins(
+        PinMask::PIN0 |
+            PinMask::PIN1 |
+            PinMask::PIN2 |
+            PinMask::PIN3 |
+            PinMask::PIN4 |
+            PinMask::PIN5 |
+            PinMask::PIN11 |
+            PinMask::PIN12 |
+            PinMask::PIN13 |
+            PinMask::PIN14 |
+            PinMask::PIN15,
+        OutputType::PushPull,
+        Speed::VeryHigh,
+        Pull::None,
+        12
+    );
+    // PG0/1 -> A10/A11, PG4/5 -> BA0/BA1, PG8 -> SDCLK, PG15 -> SDNCAS
+    gpiog.init_alternate_pins(
+        PinMask::PIN0 |
+            PinMask::PIN1 |
+            PinMask::PIN4 |
+            PinMask::PIN5 |
+            PinMask::PIN8 |
+            PinMask::PIN15,
+        OutputType::PushPull,
+        Speed::VeryHigh,
+        Pull::None,
+        12
+    );
 }
 
-impl LCD {
-    pub fn init() -> Self {
-        info!("Init LCD pins");
-        // init GPIO pins
-        // PC2 -> NCS
-        // PD12 -> RDX
-        // PD13 -> WRX
-        // PF7 -> SPI5_SCK
-        // PF8 -> SPI5_MISO
-        // PF9 -> SPI5_MOSI
-        let mut ncs = Output::new(gpio::GPIOC::take().pin(2), Speed::High);
-        let rdx = Output::new(gpio::GPIOD::take().pin(12), Speed::High);
-        let wrx = Output::new(gpio::GPIOD::take().pin(13), Speed::High);
-        gpio::GPIOF
-            ::take()
-            .init_alternate_pins(
-                PinMask::PIN7 | PinMask::PIN8 | PinMask::PIN9,
-                OutputType::PushPull,
-                Speed::VeryHigh,
-                Pull::None,
-                5
-            );
-
-        let spi = spi::SPI5::take();
-        let config = SPIConfig {
-            mode: Mode::Master,
-            bus_config: BusConfiguration::FullDuplex,
-            baud_rate: BaudRate::FpclkDiv16,
-            data_format: DataFrameFormat::Format8Bit,
-            cpol: ClockPolarity::IdleLow,
-            cpha: ClockPhase::FirstClockTransition,
-            ssm: true,
-        };
-
-        ncs.set_low().unwrap();
-        ncs.set_high().unwrap();
-
-        info!("Init SPI5 peripheral");
-        spi.init(config).unwrap();
-
-        Self { spi, ncs, rdx, wrx }
+/// Pixels in one full-screen frame
+const FRAME_PIXELS: usize =
+    PanelTiming::ACTIVE_WIDTH as usize * PanelTiming::ACTIVE_HEIGHT as usize;
+
+/// Layer 1 front and back buffers, in external SDRAM
+#[link_section = ".sdram"]
+static mut FRAME_BUFFERS: MaybeUninit<[[Rgb565; FRAME_PIXELS]; 2]> = MaybeUninit::uninit();
+
+/// Both SDRAM frame buffers, cleared to black
+///
+/// # Safety
+/// SDRAM must have been brought up by [`sdram::init`], and this may only be called once.
+unsafe fn frame_buffers() -> (&'static mut [Rgb565], &'static mut [Rgb565]) {
+    let buffers = &mut *ptr::addr_of_mut!(FRAME_BUFFERS);
+    // The .sdram section is not zeroed at start-up
+    ptr::write_bytes(buffers.as_mut_ptr(), 0, 1);
+    let [front, back] = buffers.assume_init_mut();
+
+    (front, back)
+}
+
+/// Copy the start-up image, stored as little-endian RGB565, into `buffer`, which starts
+/// at pixel `start` of the frame
+fn load_image(buffer: &mut [Rgb565], start: usize) {
+    let image: &'static [u8] = &image::IMAGE[2 * start..];
+    for (pixel, bytes) in buffer.iter_mut().zip(image.chunks_exact(2)) {
+        *pixel = Rgb565(u16::from_le_bytes([bytes[0], bytes[1]]));
     }
+}
+
+/// Side of the square sweeping across layer 1
+const SQUARE_SIZE: usize = 16;
+
+/// Draw the frame at `position` into the back buffer: the image with a square sweeping
+/// back and forth near its bottom edge
+///
+/// Only the rows the square moves along are redrawn, both buffers keep the rest of the
+/// image as loaded at start-up.
+fn draw_frame(buffers: &mut DoubleBuffer<Rgb565>, position: usize) {
+    let (width, height) = (buffers.width(), buffers.height());
+    let travel = width - SQUARE_SIZE;
+    let x = travel - (2 * (position % travel)).abs_diff(travel);
+    let y = height - 2 * SQUARE_SIZE;
+
+    let rows = y * width..(y + SQUARE_SIZE) * width;
+    load_image(&mut buffers.back()[rows.clone()], rows.start);
+    buffers.fill_rect(x, y, SQUARE_SIZE, SQUARE_SIZE, Rgb565::RED);
+}
+
+/// Height of the status bar overlaid on layer 2
+const STATUS_BAR_HEIGHT: usize = 24;
+
+/// Status bar palette indices
+const BACKGROUND_INDEX: u8 = 1;
+const HEARTBEAT_INDEX: u8 = 2;
+/// Color keyed out of the status bar; the heartbeat blinks by switching its palette
+/// entry between this and white
+const STATUS_BAR_KEY: u32 = 0x00ff_00ff;
+/// Palette entries of the activity band, cycled one step per frame
+const CYCLE_INDICES: RangeInclusive<u8> = 16..=31;
+/// Width of one activity band stripe
+const CYCLE_STRIPE: usize = 2;
+
+/// Widest the status bar gets, the long side of the panel in landscape
+const STATUS_BAR_MAX_WIDTH: usize = 320;
+
+/// Layer 2 status bar, L8 indexing [`status_bar_palette`], in external SDRAM
+#[link_section = ".sdram"]
+static mut STATUS_BAR: MaybeUninit<[u8; STATUS_BAR_MAX_WIDTH * STATUS_BAR_HEIGHT]> =
+    MaybeUninit::uninit();
+
+/// A `width` pixel wide status bar out of the SDRAM buffer, cleared to palette index 0
+///
+/// # Safety
+/// SDRAM must have been brought up by [`sdram::init`], and this may only be called once.
+unsafe fn status_bar(width: usize) -> &'static mut [u8] {
+    assert!(width <= STATUS_BAR_MAX_WIDTH, "Status bar wider than its buffer!");
+    let buffer = &mut *ptr::addr_of_mut!(STATUS_BAR);
+    ptr::write_bytes(buffer.as_mut_ptr(), 0, 1);
+
+    &mut buffer.assume_init_mut()[..width * STATUS_BAR_HEIGHT]
+}
 
-    pub fn write_reg(&mut self, reg: u8) {
-        self.wrx.set_low().unwrap();
-        self.ncs.set_low().unwrap();
-        self.spi.write_data(&[reg]).unwrap();
-        self.ncs.set_high().unwrap();
+/// The activity band ramps from blue to white and back, so it wraps around seamlessly
+fn status_bar_palette() -> Palette {
+    let mut palette = Palette::grayscale();
+    palette.set(BACKGROUND_INDEX, Palette::rgb888(Rgb565::BLUE));
+    palette.set(HEARTBEAT_INDEX, STATUS_BAR_KEY);
+
+    let (start, end) = CYCLE_INDICES.into_inner();
+    let half = (end - start + 1) as u32 / 2;
+    for index in CYCLE_INDICES {
+        let step = (index - start) as u32;
+        let level = 0xff * step.min(2 * half - step) / half;
+        palette.set(index, (level << 16) | (level << 8) | 0xff);
     }
 
-    pub fn write_data(&mut self, data: u8) {
-        self.wrx.set_high().unwrap();
-        self.ncs.set_low().unwrap();
-        self.spi.write_data(&[data]).unwrap();
-        self.ncs.set_high().unwrap();
+    palette
+}
+
+/// Draw the `width` pixel wide status bar: the activity band, then the heartbeat square
+/// at its right end
+///
+/// Drawn once; from then on the animation only changes the palette, and neither the
+/// overlay nor the background on layer 1 is redrawn.
+fn draw_status_bar(status_bar: &mut [u8], width: usize) {
+    const SIZE: usize = 16;
+    const MARGIN: usize = (STATUS_BAR_HEIGHT - SIZE) / 2;
+
+    let (start, end) = CYCLE_INDICES.into_inner();
+    status_bar.fill(BACKGROUND_INDEX);
+    for row in status_bar.chunks_exact_mut(width).skip(MARGIN).take(SIZE) {
+        let (band, heartbeat) = row[MARGIN..width - MARGIN].split_at_mut(width - 3 * MARGIN - SIZE);
+        for (x, pixel) in band.iter_mut().enumerate() {
+            *pixel = start + (x / CYCLE_STRIPE % (end - start + 1) as usize) as u8;
+        }
+        heartbeat[MARGIN..].fill(HEARTBEAT_INDEX);
     }
 }
 
+/// ILI9341 on the STM32F429 Discovery, wired to SPI5
+type Display = ILI9341<LCD<&'static mut SPI, Output, Output, Output>, systick::Delay>;
+
+fn init_lcd() -> LCD<&'static mut SPI, Output, Output, Output> {
+    info!("Init LCD pins");
+    // init GPIO pins
+    // PC2 -> NCS
+    // PD12 -> RDX
+    // PD13 -> WRX
+    // PF7 -> SPI5_SCK
+    // PF8 -> SPI5_MISO
+    // PF9 -> SPI5_MOSI
+    let mut ncs = Output::new(gpio::GPIOC::take().pin(2), Speed::High);
+    let rdx = Output::new(gpio::GPIOD::take().pin(12), Speed::High);
+    let wrx = Output::new(gpio::GPIOD::take().pin(13), Speed::High);
+    gpio::GPIOF
+        ::take()
+        .init_alternate_pins(
+            PinMask::PIN7 | PinMask::PIN8 | PinMask::PIN9,
+            OutputType::PushPull,
+            Speed::VeryHigh,
+            Pull::None,
+            5
+        );
+
+    let spi = spi::SPI5::take();
+    let config = SPIConfig {
+        mode: Mode::Master,
+        bus_config: BusConfiguration::FullDuplex,
+        baud_rate: BaudRate::FpclkDiv16,
+        data_format: DataFrameFormat::Format8Bit,
+        cpol: ClockPolarity::IdleLow,
+        cpha: ClockPhase::FirstClockTransition,
+        ssm: true,
+    };
+
+    ncs.set_low().unwrap();
+    ncs.set_high().unwrap();
+
+    info!("Init SPI5 peripheral");
+    spi.init(config).unwrap();
+
+    LCD::new(spi, ncs, rdx, wrx)
+}
+
 static mut SEMIHOSTING_LOGGER: SemihostingLogger = SemihostingLogger { host_stream: None };
 
-pub struct SemihostingLogger {
+struct SemihostingLogger {
     host_stream: Option<HostStream>,
 }
 
 impl SemihostingLogger {
-    pub fn init() -> Result<(), ()> {
+    fn init() -> Result<(), ()> {
         #[allow(static_mut_refs)]
         unsafe {
             SEMIHOSTING_LOGGER.host_stream = Some(hio::hstdout()?);
@@ -0,0 +1,231 @@
+//! FMC bring-up for the IS42S16400J SDRAM on the Discovery board
+//!
+//! 8 MB (4 banks x 4096 rows x 256 columns x 16 bits) on SDRAM bank 2, mapped at
+//! [`SDRAM_BASE`]. Statics placed in the `.sdram` section land there; they are not
+//! initialised by the runtime and must not be touched before [`init`] returns.
+
+use core::ptr;
+
+use embedded_hal::delay::DelayNs;
+
+/// Start of FMC SDRAM bank 2
+pub const SDRAM_BASE: usize = 0xd000_0000;
+pub const SDRAM_SIZE: usize = 8 * 1024 * 1024;
+
+const RCC_AHB3ENR: *mut u32 = 0x4002_3838 as *mut u32;
+const RCC_AHB3ENR_FMCEN: u32 = 1 << 0;
+
+const FMC_BASE: usize = 0xa000_0000;
+const FMC_SDCR1: *mut u32 = (FMC_BASE + 0x140) as *mut u32;
+const FMC_SDCR2: *mut u32 = (FMC_BASE + 0x144) as *mut u32;
+const FMC_SDTR1: *mut u32 = (FMC_BASE + 0x148) as *mut u32;
+const FMC_SDTR2: *mut u32 = (FMC_BASE + 0x14c) as *mut u32;
+const FMC_SDCMR: *mut u32 = (FMC_BASE + 0x150) as *mut u32;
+const FMC_SDRTR: *mut u32 = (FMC_BASE + 0x154) as *mut u32;
+const FMC_SDSR: *mut u32 = (FMC_BASE + 0x158) as *mut u32;
+const SDSR_BUSY: u32 = 1 << 5;
+
+/// SDCLK = HCLK / 2, the only ratio that keeps SDCLK in range up to 168 MHz HCLK
+const SDCLK_DIV: u32 = 2;
+/// CAS latency in SDCLK cycles, set in both the FMC and the mode register
+const CAS_LATENCY: u32 = 3;
+/// Auto-refresh commands issued during bring-up
+const AUTO_REFRESH_COUNT: u32 = 8;
+/// 4096 rows refreshed every 64 ms
+const REFRESH_PERIOD_NS: u32 = 64_000_000 / 4096;
+
+/// SDCMR command modes
+const CMD_CLOCK_ENABLE: u32 = 0b001;
+const CMD_PALL: u32 = 0b010;
+const CMD_AUTO_REFRESH: u32 = 0b011;
+const CMD_LOAD_MODE: u32 = 0b100;
+/// SDCMR command target bank 2
+const CMD_CTB2: u32 = 1 << 3;
+
+/// Mode register: burst length 1, sequential, CAS latency 3, single write burst
+const MODE_REGISTER: u32 = (CAS_LATENCY << 4) | (1 << 9);
+
+/// IS42S16400J-7 AC characteristics, in ns unless noted
+const T_MRD_CLK: u32 = 2;
+const T_XSR_NS: u32 = 70;
+const T_RAS_NS: u32 = 42;
+const T_RC_NS: u32 = 70;
+const T_WR_CLK: u32 = 2;
+const T_RP_NS: u32 = 20;
+const T_RCD_NS: u32 = 20;
+
+/// SDCLK cycles covering `ns`
+const fn cycles(ns: u32, sdclk_hz: u32) -> u32 {
+    ((ns as u64) * (sdclk_hz as u64)).div_ceil(1_000_000_000) as u32
+}
+
+/// SDRAM timings in SDCLK cycles, as programmed into SDTR
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SdramTiming {
+    /// Load mode register to active
+    pub tmrd: u32,
+    /// Exit self-refresh to active
+    pub txsr: u32,
+    /// Self-refresh time, the minimum active to precharge
+    pub tras: u32,
+    /// Row cycle, refresh to activate and activate to activate
+    pub trc: u32,
+    /// Write recovery
+    pub twr: u32,
+    /// Row precharge
+    pub trp: u32,
+    /// Row to column delay
+    pub trcd: u32,
+}
+
+impl SdramTiming {
+    /// Timings for the IS42S16400J-7 with SDCLK at `sdclk_hz`
+    ///
+    /// The write recovery time is stretched to satisfy the FMC's own rules,
+    /// TWR >= TRAS - TRCD and TWR >= TRC - TRCD - TRP (RM0090 37.7.5).
+    pub const fn new(sdclk_hz: u32) -> Self {
+        let tras = cycles(T_RAS_NS, sdclk_hz);
+        let trc = cycles(T_RC_NS, sdclk_hz);
+        let trp = cycles(T_RP_NS, sdclk_hz);
+        let trcd = cycles(T_RCD_NS, sdclk_hz);
+
+        let mut twr = T_WR_CLK;
+        if tras > trcd && twr < tras - trcd {
+            twr = tras - trcd;
+        }
+        if trc > trcd + trp && twr < trc - trcd - trp {
+            twr = trc - trcd - trp;
+        }
+
+        Self { tmrd: T_MRD_CLK, txsr: cycles(T_XSR_NS, sdclk_hz), tras, trc, twr, trp, trcd }
+    }
+
+    /// Every timing fits its 4-bit SDTR field (1 to 16 cycles)
+    pub const fn is_valid(&self) -> bool {
+        let all = [self.tmrd, self.txsr, self.tras, self.trc, self.twr, self.trp, self.trcd];
+        let mut i = 0;
+        while i < all.len() {
+            if all[i] == 0 || all[i] > 16 {
+                return false;
+            }
+            i += 1;
+        }
+        true
+    }
+
+    /// SDTR value, TRC and TRP included: for bank 2 the FMC only reads them from SDTR1
+    const fn sdtr(&self) -> u32 {
+        (self.tmrd - 1) |
+            ((self.txsr - 1) << 4) |
+            ((self.tras - 1) << 8) |
+            ((self.trc - 1) << 12) |
+            ((self.twr - 1) << 16) |
+            ((self.trp - 1) << 20) |
+            ((self.trcd - 1) << 24)
+    }
+}
+
+/// SDRTR refresh count for SDCLK at `sdclk_hz`, with the 20-cycle margin RM0090 asks
+/// for to absorb a refresh arriving during a read
+pub const fn refresh_count(sdclk_hz: u32) -> u32 {
+    cycles(REFRESH_PERIOD_NS, sdclk_hz) - 1 - 20
+}
+
+/// SDCR value for bank 2: 8 column bits, 12 row bits, 16-bit bus, 4 banks, CAS 3
+const SDCR2: u32 = 0b01 << 2 | 0b01 << 4 | 1 << 6 | CAS_LATENCY << 7;
+/// SDCR value for bank 1 carrying the fields shared by both banks: SDCLK = HCLK / 2
+/// and one HCLK of read pipe delay
+const SDCR1: u32 = SDCLK_DIV << 10 | 0b01 << 13;
+
+/// Issue an SDCMR command to bank 2 and wait for the controller to take it
+unsafe fn command(mode: u32, auto_refresh: u32, mode_register: u32) {
+    ptr::write_volatile(
+        FMC_SDCMR,
+        mode | CMD_CTB2 | ((auto_refresh - 1) << 5) | (mode_register << 9)
+    );
+    while ptr::read_volatile(FMC_SDSR) & SDSR_BUSY != 0 {}
+}
+
+/// Bring up the SDRAM with HCLK at `hclk_hz`, following RM0090 37.7.3
+///
+/// The FMC pins have to be muxed to AF12 first.
+pub fn init(hclk_hz: u32, delay: &mut impl DelayNs) {
+    let sdclk_hz = hclk_hz / SDCLK_DIV;
+    let timing = SdramTiming::new(sdclk_hz);
+    assert!(timing.is_valid(), "SDRAM timings out of range");
+
+    unsafe {
+        ptr::write_volatile(RCC_AHB3ENR, ptr::read_volatile(RCC_AHB3ENR) | RCC_AHB3ENR_FMCEN);
+
+        ptr::write_volatile(FMC_SDCR1, SDCR1);
+        ptr::write_volatile(FMC_SDCR2, SDCR2);
+        ptr::write_volatile(FMC_SDTR1, timing.sdtr());
+        ptr::write_volatile(FMC_SDTR2, timing.sdtr());
+
+        command(CMD_CLOCK_ENABLE, 1, 0);
+        // Power-up: at least 100 us of stable clock before the first real command
+        delay.delay_us(100);
+        command(CMD_PALL, 1, 0);
+        command(CMD_AUTO_REFRESH, AUTO_REFRESH_COUNT, 0);
+        command(CMD_LOAD_MODE, 1, MODE_REGISTER);
+
+        ptr::write_volatile(FMC_SDRTR, refresh_count(sdclk_hz) << 1);
+    }
+}
+
+// Checked at build time for 90 MHz SDCLK, where everything but TWR matches ST's setup
+// for this board (ST programs TWR = 2, TWR >= TRC - TRCD - TRP makes it 3), and for the
+// 72 MHz HCLK this firmware runs at.
+const _: () = {
+    let timing = SdramTiming::new(90_000_000);
+    assert!(timing.tmrd == 2 && timing.txsr == 7 && timing.tras == 4 && timing.trc == 7);
+    assert!(timing.trp == 2 && timing.trcd == 2 && timing.twr == 3);
+    assert!(timing.is_valid());
+    assert!(refresh_count(90_000_000) == 1386);
+
+    let timing = SdramTiming::new(36_000_000);
+    assert!(timing.tmrd == 2 && timing.txsr == 3 && timing.tras == 2 && timing.trc == 3);
+    assert!(timing.trp == 1 && timing.trcd == 1 && timing.twr == 2);
+    assert!(timing.is_valid());
+    assert!(refresh_count(36_000_000) == 542);
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timing_90mhz() {
+        let timing = SdramTiming::new(90_000_000);
+        let expected = SdramTiming { tmrd: 2, txsr: 7, tras: 4, trc: 7, twr: 3, trp: 2, trcd: 2 };
+        assert_eq!(timing, expected);
+        assert_eq!(timing.sdtr(), 0x0112_6361);
+        assert_eq!(refresh_count(90_000_000), 1386);
+    }
+
+    #[test]
+    fn twr_stretched() {
+        // The datasheet TWR holds at a slow clock, TRC - TRCD - TRP takes over at a fast one
+        let timing = SdramTiming::new(36_000_000);
+        assert_eq!(timing.twr, T_WR_CLK);
+        let timing = SdramTiming::new(140_000_000);
+        assert_eq!((timing.tras, timing.trc, timing.trp, timing.trcd), (6, 10, 3, 3));
+        assert_eq!(timing.twr, 4);
+    }
+
+    #[test]
+    fn refresh_count_scales() {
+        assert_eq!(refresh_count(36_000_000), 542);
+        assert_eq!(refresh_count(84_000_000), 1292);
+    }
+
+    #[test]
+    fn out_of_range() {
+        // TXSR needs 18 cycles at 250 MHz, one more than the 4-bit field holds
+        let timing = SdramTiming::new(250_000_000);
+        assert_eq!(timing.txsr, 18);
+        assert!(!timing.is_valid());
+        assert!(!SdramTiming::new(0).is_valid());
+        assert!(SdramTiming::new(1_000_000).is_valid());
+    }
+}
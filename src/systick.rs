@@ -0,0 +1,61 @@
+//! SysTick-driven millisecond clock and blocking delays
+//!
+//! SysTick counts core clock cycles and interrupts once per millisecond. The interrupt
+//! advances [`millis`]; [`Delay`] counts the cycles themselves, so delays hold at any
+//! optimisation level and resolve well below a millisecond.
+
+use core::sync::atomic::{ AtomicU32, Ordering };
+
+use cortex_m::peripheral::{ syst::SystClkSource, SYST };
+use cortex_m_rt::exception;
+use embedded_hal::delay::DelayNs;
+
+static MILLIS: AtomicU32 = AtomicU32::new(0);
+
+/// Start SysTick at 1 kHz off the core clock running at `sysclk_hz`
+///
+/// SYST is consumed so nothing else can reprogram the reload value the delays rely on.
+pub fn init(mut syst: SYST, sysclk_hz: u32) -> Delay {
+    let reload = sysclk_hz / 1_000 - 1;
+
+    syst.set_clock_source(SystClkSource::Core);
+    syst.set_reload(reload);
+    syst.clear_current();
+    syst.enable_interrupt();
+    syst.enable_counter();
+
+    Delay { sysclk_hz, reload }
+}
+
+/// Milliseconds since [`init`], wrapping after about 49 days
+pub fn millis() -> u32 {
+    MILLIS.load(Ordering::Relaxed)
+}
+
+/// Blocking delay counting SysTick cycles
+///
+/// Cheap to copy, every user can own one; they all read the same free-running counter.
+#[derive(Clone, Copy, Debug)]
+pub struct Delay {
+    sysclk_hz: u32,
+    reload: u32,
+}
+
+impl DelayNs for Delay {
+    fn delay_ns(&mut self, ns: u32) {
+        let mut remaining = ((ns as u64) * (self.sysclk_hz as u64)).div_ceil(1_000_000_000);
+        let mut last = SYST::get_current();
+        while remaining > 0 {
+            // SysTick counts down and reloads to `reload` after reaching zero
+            let now = SYST::get_current();
+            let elapsed = if now <= last { last - now } else { last + (self.reload + 1 - now) };
+            remaining = remaining.saturating_sub(elapsed as u64);
+            last = now;
+        }
+    }
+}
+
+#[exception]
+fn SysTick() {
+    MILLIS.fetch_add(1, Ordering::Relaxed);
+}
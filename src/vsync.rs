@@ -0,0 +1,112 @@
+//! LTDC interrupts: frame counting and vertical blanking
+//!
+//! The line interrupt fires as the last active line has been scanned out, i.e. at the
+//! start of vertical blanking. Each one advances [`frame_count`] and wakes
+//! [`wait_for_vblank`]. The register reload interrupt advances [`reload_count`] and wakes
+//! [`wait_for_reload`] once shadow registers written with a vertical blanking reload (a
+//! page flip) have taken effect.
+//!
+//! There is no device crate, so the LTDC global interrupt is served from
+//! `DefaultHandler`.
+
+use core::{ ptr, sync::atomic::{ AtomicU32, Ordering } };
+
+use cortex_m::{ interrupt::InterruptNumber, peripheral::NVIC };
+use cortex_m_rt::exception;
+use example_ili9341::drivers::ili9341::PanelTiming;
+
+const LTDC_BASE: usize = 0x4001_6800;
+/// Interrupt enable register
+const LTDC_IER: *mut u32 = (LTDC_BASE + 0x34) as *mut u32;
+/// Interrupt status register
+const LTDC_ISR: *const u32 = (LTDC_BASE + 0x38) as *const u32;
+/// Interrupt clear register
+const LTDC_ICR: *mut u32 = (LTDC_BASE + 0x3c) as *mut u32;
+/// Line interrupt position configuration register
+const LTDC_LIPCR: *mut u32 = (LTDC_BASE + 0x40) as *mut u32;
+
+/// Line interrupt
+const LIF: u32 = 1 << 0;
+/// Register reload interrupt
+const RRIF: u32 = 1 << 3;
+
+/// LTDC global interrupt, position 88 in the vector table
+#[derive(Clone, Copy, Debug)]
+struct LtdcInterrupt;
+
+unsafe impl InterruptNumber for LtdcInterrupt {
+    fn number(self) -> u16 {
+        88
+    }
+}
+
+static FRAMES: AtomicU32 = AtomicU32::new(0);
+static RELOADS: AtomicU32 = AtomicU32::new(0);
+
+/// Start counting frames of a panel driven with `timing` and `height` active lines
+///
+/// The LTDC has to be initialised first.
+pub fn init(timing: &PanelTiming, height: u16) {
+    unsafe {
+        // Lines count from the start of the sync pulse
+        ptr::write_volatile(LTDC_LIPCR, (timing.vsync + timing.vbp + height) as u32);
+        ptr::write_volatile(LTDC_ICR, LIF | RRIF);
+        ptr::write_volatile(LTDC_IER, ptr::read_volatile(LTDC_IER) | LIF | RRIF);
+
+        NVIC::unmask(LtdcInterrupt);
+    }
+}
+
+/// Frames scanned out since [`init`], wrapping after about two years at 65 Hz
+pub fn frame_count() -> u32 {
+    FRAMES.load(Ordering::Relaxed)
+}
+
+/// Reloads of the shadow registers since [`init`]
+pub fn reload_count() -> u32 {
+    RELOADS.load(Ordering::Relaxed)
+}
+
+/// Sleep until the next vertical blanking period starts
+///
+/// Returns with the whole blanking period ahead, the time to flip pages or update a
+/// CLUT without tearing.
+pub fn wait_for_vblank() {
+    let frame = frame_count();
+    while frame_count() == frame {
+        cortex_m::asm::wfi();
+    }
+}
+
+/// Sleep until the shadow registers have reloaded since [`reload_count`] returned `count`
+///
+/// Read the count before requesting the reload, so one that completes right away is
+/// not missed.
+pub fn wait_for_reload(count: u32) {
+    while reload_count() == count {
+        cortex_m::asm::wfi();
+    }
+}
+
+fn on_interrupt() {
+    let isr = unsafe { ptr::read_volatile(LTDC_ISR) };
+    unsafe {
+        ptr::write_volatile(LTDC_ICR, isr & (LIF | RRIF));
+    }
+
+    if isr & RRIF != 0 {
+        RELOADS.fetch_add(1, Ordering::Relaxed);
+    }
+    if isr & LIF != 0 {
+        FRAMES.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+#[exception]
+unsafe fn DefaultHandler(irqn: i16) {
+    if irqn == LtdcInterrupt.number() as i16 {
+        on_interrupt();
+    } else {
+        panic!("Unhandled interrupt {}", irqn);
+    }
+}
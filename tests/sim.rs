@@ -0,0 +1,20 @@
+//! The driver against the `sim` feature's controller model, through the public API only
+
+use example_ili9341::drivers::{
+    ili9341::{ InterfaceMode, PowerState, Rgb565, Verify, ILI9341 },
+    ili9341_sim::{ ILI9341Sim, NoDelay },
+};
+
+#[test]
+fn init_and_fill() {
+    let mut ili9341 = ILI9341::init(ILI9341Sim::new(), NoDelay, Verify::Readback).unwrap();
+    assert_eq!(ili9341.power_state(), PowerState::On);
+
+    ili9341.set_interface_mode(InterfaceMode::Mcu).unwrap();
+    let (width, height) = (ili9341.width() as u16, ili9341.height() as u16);
+    ili9341.fill_rect(0, 0, width, height, Rgb565::RED).unwrap();
+
+    let (sim, _) = ili9341.release();
+    assert!(sim.display_on && !sim.sleeping);
+    assert!(sim.gram.iter().all(|&pixel| pixel == Rgb565::RED.0));
+}